@@ -1,12 +1,24 @@
+#[cfg(unix)]
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
 use celcat::{
+    fetch::cache::DiskCache,
     fetchable::{
-        calendar::{CalView, CalendarData, CalendarDataRequest},
+        calendar::CalendarData,
         event::{Event, EventRequest},
     },
-    Celcat, CourseId, Student, StudentId,
+    ics::VCalendar,
+    Celcat, CourseId, DynEntityId, DynEntityType, ResourceType,
 };
 use chrono::NaiveDateTime;
 use clap::Parser;
+use futures::TryStreamExt;
 
 #[derive(Debug, Parser)]
 struct Opts {
@@ -14,6 +26,18 @@ struct Opts {
     username: String,
     #[clap(short, long)]
     password: String,
+    /// Record the responses in this directory
+    #[clap(long, conflicts_with = "replay")]
+    record: Option<PathBuf>,
+    /// Replay the responses recorded in this directory instead of using the network
+    #[clap(long)]
+    replay: Option<PathBuf>,
+    /// Cache the responses in this directory for an hour
+    #[clap(long, conflicts_with = "replay")]
+    cache: Option<PathBuf>,
+    /// Restore the session saved in this file, and save it there afterwards
+    #[clap(long, conflicts_with = "replay")]
+    session: Option<PathBuf>,
     #[clap(subcommand)]
     subcmd: SubCommand,
 }
@@ -21,6 +45,8 @@ struct Opts {
 #[derive(Debug, Parser)]
 enum SubCommand {
     Calendar {
+        #[clap(short, long, default_value = "student")]
+        res_type: DynEntityType,
         #[clap(short, long)]
         start: NaiveDateTime,
         #[clap(short, long)]
@@ -32,6 +58,67 @@ enum SubCommand {
         #[clap(short, long)]
         id: String,
     },
+    /// List every resource of a type
+    Resources {
+        #[clap(short, long)]
+        res_type: DynEntityType,
+        #[clap(short, long, default_value = "")]
+        search: String,
+    },
+    /// Write the calendar of a resource as an iCalendar file
+    Ics {
+        #[clap(short, long)]
+        res_type: DynEntityType,
+        #[clap(short, long)]
+        start: NaiveDateTime,
+        #[clap(short, long)]
+        end: NaiveDateTime,
+        #[clap(short, long, required = true)]
+        id: Vec<String>,
+        /// Write to this file instead of stdout
+        #[clap(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+async fn fetch_ics<T>(
+    celcat: &Celcat,
+    res_type: T,
+    ids: Vec<T::Id>,
+    start: NaiveDateTime,
+    end: NaiveDateTime,
+) -> Result<VCalendar, Box<dyn std::error::Error>>
+where
+    T: ResourceType,
+{
+    let courses = celcat
+        .fetch_calendar_with_details(celcat.calendar_request(res_type, ids, start, end))
+        .await?;
+
+    let mut cal = VCalendar::new();
+    for (course, event) in &courses {
+        match event {
+            Ok(event) => cal.push(course, Some(event)),
+            Err(e) => {
+                eprintln!("no details for course {}: {}", course.id.0, e);
+                cal.push(course, None);
+            }
+        }
+    }
+    Ok(cal)
+}
+
+/// Writes a file only readable by the user, as it gives access to their account.
+fn write_private(path: &Path, contents: &str) -> io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    let mut file = options.open(path)?;
+    // The mode is only set when the file is created
+    #[cfg(unix)]
+    file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    file.write_all(contents.as_bytes())
 }
 
 #[tokio::main]
@@ -39,21 +126,42 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     env_logger::init();
     let opts: Opts = Opts::parse();
 
-    let mut celcat = Celcat::new("https://services-web.u-cergy.fr/calendar").await?;
-    celcat.login(&opts.username, &opts.password).await?;
+    let session = match &opts.session {
+        Some(path) if path.exists() => Some(serde_json::from_str(&fs::read_to_string(path)?)?),
+        _ => None,
+    };
+    let mut celcat = match &opts.replay {
+        Some(dir) => Celcat::replay(dir).await?,
+        None => {
+            let mut builder = Celcat::builder("https://services-web.u-cergy.fr/calendar");
+            if let Some(dir) = &opts.cache {
+                builder = builder.cache(DiskCache::new(dir), Duration::from_secs(60 * 60));
+            }
+            if let Some(session) = session {
+                builder = builder.session(session);
+            }
+            builder.build().await?
+        }
+    };
+    if let Some(dir) = &opts.record {
+        celcat.record_to(dir);
+    }
+    if opts.session.as_deref().is_some_and(|path| path.exists()) {
+        celcat.set_auto_relogin(true);
+        celcat.set_credentials(&opts.username, &opts.password);
+    } else {
+        celcat.login(&opts.username, &opts.password).await?;
+    }
 
     match opts.subcmd {
-        SubCommand::Calendar { start, end, id } => {
-            let res: CalendarData<Student> = celcat
-                .fetch(CalendarDataRequest {
-                    start,
-                    end,
-                    res_type: Student,
-                    cal_view: CalView::Month,
-                    federation_ids: StudentId(id),
-                    colour_scheme: 3,
-                })
-                .await?;
+        SubCommand::Calendar {
+            res_type,
+            start,
+            end,
+            id,
+        } => {
+            let req = celcat.calendar_request(res_type, vec![DynEntityId(Some(id))], start, end);
+            let res: CalendarData<DynEntityType> = celcat.fetch(req).await?;
             println!("{:#?}", res);
         }
         SubCommand::Event { id } => {
@@ -64,6 +172,35 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .await?;
             println!("{:#?}", res);
         }
+        SubCommand::Resources { res_type, search } => {
+            let mut resources = Box::pin(celcat.resources(res_type, &search));
+            while let Some(res) = resources.try_next().await? {
+                println!(
+                    "{}\t{}\t{}",
+                    res.id.0.unwrap_or_default(),
+                    res.text,
+                    res.dept
+                );
+            }
+        }
+        SubCommand::Ics {
+            res_type,
+            start,
+            end,
+            id,
+            output,
+        } => {
+            let ids = id.into_iter().map(|id| DynEntityId(Some(id))).collect();
+            let cal = fetch_ics(&celcat, res_type, ids, start, end).await?;
+            match output {
+                Some(path) => fs::write(path, cal.to_string())?,
+                None => print!("{}", cal),
+            }
+        }
+    }
+
+    if let Some(path) = &opts.session {
+        write_private(path, &serde_json::to_string(&celcat.session())?)?;
     }
 
     Ok(())
@@ -0,0 +1,125 @@
+//! # Blocking client
+//!
+//! A synchronous version of [`Celcat`](crate::Celcat), built on
+//! [`reqwest::blocking`], for programs which don't need an async runtime.
+//!
+//! It must not be used from within an async runtime.
+
+use log::info;
+
+use crate::{
+    fetch::{check, check_login, login_form, parse_token, transport::HttpResponse, FetchError},
+    fetchable::Fetchable,
+    form,
+};
+
+#[derive(Debug, Clone)]
+pub struct Celcat {
+    client: reqwest::blocking::Client,
+    address: String,
+    token: String,
+}
+
+impl Celcat {
+    pub fn new<S>(address: S) -> Result<Self, FetchError>
+    where
+        S: AsRef<str>,
+    {
+        let client = reqwest::blocking::Client::builder()
+            .cookie_store(true)
+            .build()?;
+        Self::with_client(address, client)
+    }
+
+    /// Creates a client using `client`, which must have been built with a cookie store.
+    pub fn with_client<S>(address: S, client: reqwest::blocking::Client) -> Result<Self, FetchError>
+    where
+        S: AsRef<str>,
+    {
+        info!("fetching celcat token");
+        let address = address.as_ref().to_owned();
+        let res = read(client.get(format!("{}/LdapLogin", address)).send()?)?;
+        let token = parse_token(&res.body)?;
+
+        Ok(Self {
+            client,
+            address,
+            token,
+        })
+    }
+
+    pub fn login(&mut self, username: &str, password: &str) -> Result<(), FetchError> {
+        info!("fetching celcat federation ids");
+        let form = login_form(&self.token, username, password)?;
+        let res = self
+            .client
+            .post(format!("{}/LdapLogin/Logon", self.address))
+            .form(&form)
+            .send()?;
+        check_login(read(res)?)
+    }
+
+    pub fn fetch<F>(&self, req: F::Request) -> Result<F, FetchError>
+    where
+        F: Fetchable,
+    {
+        let form = form::to_pairs(&req).map_err(FetchError::Encode)?;
+        let res = self
+            .client
+            .post(format!("{}/Home/{}", self.address, F::METHOD_NAME))
+            .form(&form)
+            .send()?;
+        check(read(res)?)
+    }
+}
+
+fn read(res: reqwest::blocking::Response) -> Result<HttpResponse, FetchError> {
+    Ok(HttpResponse {
+        status: res.status().as_u16(),
+        url: res.url().to_string(),
+        content_type: res
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|ct| ct.to_str().ok())
+            .map(str::to_owned),
+        body: res.text()?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        fetchable::event::{Event, EventRequest},
+        mock::{Fixtures, MockServer},
+        CourseId,
+    };
+    use serde_json::json;
+
+    #[test]
+    fn login_and_fetch() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let mut fixtures = Fixtures::default();
+        fixtures.events.insert(
+            "1".to_owned(),
+            json!({
+                "federationId": null,
+                "entityType": 0,
+                "elements": []
+            }),
+        );
+        let server = rt.block_on(MockServer::start(fixtures)).unwrap();
+
+        let mut celcat = Celcat::new(server.address()).unwrap();
+        assert!(matches!(
+            celcat.login("username", "wrong"),
+            Err(FetchError::InvalidCredentials)
+        ));
+        celcat.login("username", "password").unwrap();
+        celcat
+            .fetch::<Event>(EventRequest {
+                event_id: CourseId("1".to_owned()),
+            })
+            .unwrap();
+    }
+}
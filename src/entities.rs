@@ -8,8 +8,19 @@
 //! An *entity* can be a *resource*, in which case it has an associated ID.
 //! If it isn't a *resource*, is doesn't have an ID (`null` in JSON),
 //! and we represent its type with [`Unknown`], and its ID with [`UnknownId`].
+//!
+//! When the type is only known at runtime (from a configuration file or a
+//! command line argument, for example), [`DynEntityType`] and [`DynEntityId`]
+//! can be used in place of the types above.
 
-use std::{concat, convert::TryFrom, fmt::Debug, stringify};
+use std::{
+    concat,
+    convert::TryFrom,
+    error::Error,
+    fmt::{self, Debug, Display},
+    str::FromStr,
+    stringify,
+};
 
 use paste::paste;
 use serde::{Deserialize, Serialize};
@@ -137,7 +148,7 @@ macro_rules! entities {
 
                 if_unknown! {
                     if $r {
-                        #[derive(Debug, Default, Clone, PartialEq)]
+                        #[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
                         #[derive(Serialize, Deserialize)]
                         #[serde(from = "()", into = "()")]
                         pub struct [<$r Id>];
@@ -150,7 +161,7 @@ macro_rules! entities {
                             }
                         }
                     } else {
-                        #[derive(Debug, Default, Clone, PartialEq)]
+                        #[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
                         #[derive(Serialize, Deserialize)]
                         #[repr(transparent)]
                         pub struct [<$r Id>](pub String);
@@ -164,8 +175,94 @@ macro_rules! entities {
                         impl ResourceId for [<$r Id>] {}
                     }
                 }
+
+                impl From<$r> for DynEntityType {
+                    fn from(_: $r) -> Self {
+                        Self::$r
+                    }
+                }
+
+                impl TryFrom<DynEntityType> for $r {
+                    type Error = &'static str;
+                    fn try_from(t: DynEntityType) -> Result<Self, Self::Error> {
+                        Self::try_from(u8::from(t))
+                    }
+                }
+
+                if_unknown! {
+                    if $r {
+                        impl From<[<$r Id>]> for DynEntityId {
+                            fn from(_: [<$r Id>]) -> Self {
+                                Self(None)
+                            }
+                        }
+                    } else {
+                        impl From<[<$r Id>]> for DynEntityId {
+                            fn from(id: [<$r Id>]) -> Self {
+                                Self(Some(id.0))
+                            }
+                        }
+                    }
+                }
             }
         )+
+
+        paste! {
+            /// An *entity* type only known at runtime.
+            ///
+            /// It is displayed and parsed from its lowercase name (like `student`),
+            /// and serialized as its code, like the static types.
+            #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+            #[derive(Serialize, Deserialize)]
+            #[serde(try_from = "u8", into = "u8")]
+            pub enum DynEntityType {
+                $(
+                    #[doc = "See [`" $r "`]."]
+                    $r,
+                )+
+            }
+
+            impl From<DynEntityType> for u8 {
+                fn from(t: DynEntityType) -> Self {
+                    match t {
+                        $(DynEntityType::$r => $n,)+
+                    }
+                }
+            }
+
+            impl TryFrom<u8> for DynEntityType {
+                type Error = &'static str;
+                fn try_from(n: u8) -> Result<Self, Self::Error> {
+                    match n {
+                        $($n => Ok(Self::$r),)+
+                        _ => Err("expected an entity type code"),
+                    }
+                }
+            }
+
+            impl Display for DynEntityType {
+                fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                    f.write_str(match self {
+                        $(Self::$r => stringify!([<$r:lower>]),)+
+                    })
+                }
+            }
+
+            impl FromStr for DynEntityType {
+                type Err = ParseEntityTypeError;
+                fn from_str(s: &str) -> Result<Self, Self::Err> {
+                    $(
+                        if s.eq_ignore_ascii_case(stringify!($r)) {
+                            return Ok(Self::$r);
+                        }
+                    )+
+                    s.parse::<u8>()
+                        .ok()
+                        .and_then(|n| Self::try_from(n).ok())
+                        .ok_or_else(|| ParseEntityTypeError(s.to_owned()))
+                }
+            }
+        }
     };
 }
 
@@ -181,6 +278,38 @@ entities! {
     Course = 107,
 }
 
+impl private::Sealed for DynEntityType {}
+impl EntityType for DynEntityType {
+    type Id = DynEntityId;
+}
+/// Celcat refuses requests made with [`DynEntityType::Unknown`].
+impl ResourceType for DynEntityType {
+    type Id = DynEntityId;
+}
+
+/// An *entity* ID only known at runtime.
+///
+/// It is `None` for an entity which isn't a *resource*.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct DynEntityId(pub Option<String>);
+
+impl private::Sealed for DynEntityId {}
+impl EntityId for DynEntityId {}
+impl ResourceId for DynEntityId {}
+
+/// Error returned when parsing an unknown [`DynEntityType`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseEntityTypeError(String);
+
+impl Display for ParseEntityTypeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown entity type `{}`", self.0)
+    }
+}
+
+impl Error for ParseEntityTypeError {}
+
 mod private {
     /// Empty trait that no struct/enum can implement outside of this crate.
     ///
@@ -234,4 +363,47 @@ mod tests {
         );
         from_value::<RoomId>(json!(1172976)).unwrap_err();
     }
+
+    #[test]
+    fn parse_dyn_entity_type() {
+        assert_eq!("student".parse(), Ok(DynEntityType::Student));
+        assert_eq!("Room".parse(), Ok(DynEntityType::Room));
+        assert_eq!("103".parse(), Ok(DynEntityType::Group));
+        assert_eq!("0".parse(), Ok(DynEntityType::Unknown));
+        "teacher".parse::<DynEntityType>().unwrap_err();
+        "99".parse::<DynEntityType>().unwrap_err();
+    }
+
+    #[test]
+    fn display_dyn_entity_type() {
+        assert_eq!(DynEntityType::Equipment.to_string(), "equipment");
+        assert_eq!(DynEntityType::Unknown.to_string(), "unknown");
+    }
+
+    #[test]
+    fn serde_dyn_entity_type() {
+        assert_eq!(to_value(DynEntityType::Staff).unwrap(), json!(101));
+        assert_eq!(
+            from_value::<DynEntityType>(json!(107)).unwrap(),
+            DynEntityType::Course
+        );
+        from_value::<DynEntityType>(json!(108)).unwrap_err();
+    }
+
+    #[test]
+    fn convert_dyn_entity_type() {
+        assert_eq!(DynEntityType::from(Module), DynEntityType::Module);
+        Module::try_from(DynEntityType::Module).unwrap();
+        Module::try_from(DynEntityType::Room).unwrap_err();
+    }
+
+    #[test]
+    fn serde_dyn_entity_id() {
+        assert_eq!(to_value(DynEntityId(None)).unwrap(), json!(null));
+        assert_eq!(
+            from_value::<DynEntityId>(json!("1172947")).unwrap(),
+            DynEntityId::from(RoomId("1172947".to_owned()))
+        );
+        assert_eq!(DynEntityId::from(UnknownId), DynEntityId(None));
+    }
 }
@@ -0,0 +1,229 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use reqwest::cookie::Jar;
+
+use super::{
+    cache::{Cache, CacheLayer},
+    limit::Limiter,
+    retry::RetryPolicy,
+    session::Session,
+    transport::{ReqwestTransport, Transport},
+    Celcat, FetchError, DETAILS_CONCURRENCY,
+};
+use crate::fetchable::{
+    calendar::{CalView, ColourScheme},
+    Fetchable,
+};
+
+/// A builder to configure a [`Celcat`] client.
+///
+/// The HTTP options (timeout, user agent and proxy) are ignored when a
+/// [`client`](Self::client) or a [`transport`](Self::transport) is given.
+#[derive(Debug)]
+pub struct CelcatBuilder {
+    address: String,
+    timeout: Option<Duration>,
+    user_agent: Option<String>,
+    proxy: Option<reqwest::Proxy>,
+    transport: Option<Arc<dyn Transport>>,
+    retry: RetryPolicy,
+    max_concurrency: Option<usize>,
+    /// The minimal interval between two requests.
+    rate_limit: Option<Duration>,
+    cache: Option<(Arc<dyn Cache>, Duration)>,
+    cache_ttls: HashMap<&'static str, Duration>,
+    colour_scheme: ColourScheme,
+    cal_view: CalView,
+    session: Option<Session>,
+}
+
+impl CelcatBuilder {
+    pub(super) fn new(address: String) -> Self {
+        Self {
+            address,
+            timeout: None,
+            user_agent: None,
+            proxy: None,
+            transport: None,
+            retry: RetryPolicy::none(),
+            max_concurrency: None,
+            rate_limit: None,
+            cache: None,
+            cache_ttls: HashMap::new(),
+            colour_scheme: ColourScheme::default(),
+            cal_view: CalView::default(),
+            session: None,
+        }
+    }
+
+    /// Timeout of each request.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn user_agent<S>(mut self, user_agent: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    pub fn proxy(mut self, proxy: reqwest::Proxy) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    /// Uses `client` to send the requests.
+    ///
+    /// It must have been built with a cookie store.
+    pub fn client(self, client: reqwest::Client) -> Self {
+        self.transport(client)
+    }
+
+    /// Sends the requests through `transport`.
+    pub fn transport<T>(mut self, transport: T) -> Self
+    where
+        T: Transport + 'static,
+    {
+        self.transport = Some(Arc::new(transport));
+        self
+    }
+
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Maximum number of requests sent at the same time by [`Celcat::fetch`],
+    /// and of events fetched at once by [`Celcat::fetch_calendar_with_details`].
+    ///
+    /// The limit is shared with the clones of the client.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_concurrency` is zero, as no request could ever be sent.
+    pub fn max_concurrency(mut self, max_concurrency: usize) -> Self {
+        assert!(
+            max_concurrency > 0,
+            "the concurrency limit must be positive"
+        );
+        self.max_concurrency = Some(max_concurrency);
+        self
+    }
+
+    /// Maximum number of requests sent each second by [`Celcat::fetch`].
+    ///
+    /// The limit is shared with the clones of the client.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `requests_per_second` isn't positive,
+    /// or is so small that the interval between two requests overflows.
+    pub fn rate_limit(mut self, requests_per_second: f64) -> Self {
+        assert!(requests_per_second > 0.0, "the rate limit must be positive");
+        let interval = Duration::try_from_secs_f64(1.0 / requests_per_second)
+            .expect("the rate limit is too small");
+        self.rate_limit = Some(interval);
+        self
+    }
+
+    /// Caches the successful responses in `cache`, for `ttl` unless
+    /// [`cache_ttl`](Self::cache_ttl) sets another TTL for their method.
+    ///
+    /// See the [`cache`](super::cache) module.
+    pub fn cache<C>(mut self, cache: C, ttl: Duration) -> Self
+    where
+        C: Cache + 'static,
+    {
+        self.cache = Some((Arc::new(cache), ttl));
+        self
+    }
+
+    /// Caches the responses to `F` for `ttl`. A zero TTL disables their caching.
+    pub fn cache_ttl<F>(mut self, ttl: Duration) -> Self
+    where
+        F: Fetchable,
+    {
+        self.cache_ttls.insert(F::METHOD_NAME, ttl);
+        self
+    }
+
+    /// Colour scheme used by [`Celcat::calendar_request`].
+    pub fn colour_scheme(mut self, colour_scheme: ColourScheme) -> Self {
+        self.colour_scheme = colour_scheme;
+        self
+    }
+
+    /// View used by [`Celcat::calendar_request`].
+    pub fn cal_view(mut self, cal_view: CalView) -> Self {
+        self.cal_view = cal_view;
+        self
+    }
+
+    /// Restores `session` instead of starting a new one.
+    ///
+    /// Its cookies are ignored if the transport doesn't support
+    /// [`Transport::set_cookies`].
+    pub fn session(mut self, session: Session) -> Self {
+        self.session = Some(session);
+        self
+    }
+
+    /// Builds the client, and fetches the token needed to log in,
+    /// unless a session is restored.
+    pub async fn build(self) -> Result<Celcat, FetchError> {
+        let transport = match self.transport {
+            Some(transport) => transport,
+            None => {
+                let jar = Arc::new(Jar::default());
+                let mut client = reqwest::Client::builder().cookie_provider(Arc::clone(&jar));
+                if let Some(timeout) = self.timeout {
+                    client = client.timeout(timeout);
+                }
+                if let Some(user_agent) = self.user_agent {
+                    client = client.user_agent(user_agent);
+                }
+                if let Some(proxy) = self.proxy {
+                    client = client.proxy(proxy);
+                }
+                Arc::new(ReqwestTransport::new(client.build()?, jar))
+            }
+        };
+        let (token, username) = match self.session {
+            Some(session) => {
+                transport.set_cookies(&self.address, &session.cookies);
+                (session.token, session.username)
+            }
+            None => (Celcat::fetch_token(&*transport, &self.address).await?, None),
+        };
+
+        let cache_ttls = self.cache_ttls;
+        let address = self.address.clone();
+        let cache = self.cache.map(|(cache, ttl)| {
+            let mut cache = CacheLayer::new(cache, ttl, cache_ttls, address);
+            cache.set_account(username.clone());
+            cache
+        });
+
+        Ok(Celcat {
+            transport,
+            address: self.address,
+            token: Arc::new(Mutex::new(token)),
+            username,
+            credentials: None,
+            auto_relogin: false,
+            retry: self.retry,
+            limiter: Arc::new(Limiter::new(self.max_concurrency, self.rate_limit)),
+            details_concurrency: self.max_concurrency.unwrap_or(DETAILS_CONCURRENCY),
+            cache,
+            colour_scheme: self.colour_scheme,
+            cal_view: self.cal_view,
+        })
+    }
+}
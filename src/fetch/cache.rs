@@ -0,0 +1,399 @@
+//! Caching of Celcat responses.
+//!
+//! A [`Cache`] given to [`CelcatBuilder::cache`](super::CelcatBuilder::cache)
+//! stores the successful responses to [`Celcat::fetch`](super::Celcat::fetch),
+//! keyed by the client and the request, and serves them back until they are
+//! older than the TTL of their method.
+//! [`MemoryCache`] keeps them in memory, and [`DiskCache`] in a directory.
+
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use super::{record::request_key, transport::HttpResponse};
+
+/// A cached response.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub stored_at: SystemTime,
+    pub response: HttpResponse,
+}
+
+/// Stores the responses of a [`Celcat`](super::Celcat) client.
+///
+/// A cache is best-effort: failing to store an entry must not fail the request,
+/// so the errors are only logged.
+pub trait Cache: Debug + Send + Sync {
+    fn get(&self, key: &str) -> Option<CacheEntry>;
+
+    fn insert(&self, key: &str, entry: CacheEntry);
+
+    fn remove(&self, key: &str);
+
+    fn clear(&self);
+}
+
+/// An in-memory cache, evicting the least recently used entry once full.
+#[derive(Debug)]
+pub struct MemoryCache {
+    capacity: usize,
+    state: Mutex<LruState>,
+}
+
+#[derive(Debug, Default)]
+struct LruState {
+    /// Each entry with the tick of its last use.
+    entries: HashMap<String, (CacheEntry, u64)>,
+    tick: u64,
+}
+
+impl MemoryCache {
+    /// A cache holding at most `capacity` responses.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            state: Mutex::default(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Cache for MemoryCache {
+    fn get(&self, key: &str) -> Option<CacheEntry> {
+        let mut state = self.state.lock().unwrap();
+        state.tick += 1;
+        let tick = state.tick;
+        state.entries.get_mut(key).map(|(entry, used)| {
+            *used = tick;
+            entry.clone()
+        })
+    }
+
+    fn insert(&self, key: &str, entry: CacheEntry) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        state.tick += 1;
+        let tick = state.tick;
+        if !state.entries.contains_key(key) && state.entries.len() >= self.capacity {
+            let lru = state
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(key, _)| key.clone());
+            if let Some(lru) = lru {
+                state.entries.remove(&lru);
+            }
+        }
+        state.entries.insert(key.to_owned(), (entry, tick));
+    }
+
+    fn remove(&self, key: &str) {
+        self.state.lock().unwrap().entries.remove(key);
+    }
+
+    fn clear(&self) {
+        self.state.lock().unwrap().entries.clear();
+    }
+}
+
+/// A cache storing each response as a JSON file in a directory,
+/// so that it persists across runs.
+///
+/// The directory can be shared: [`Cache::clear`] only removes the entries.
+#[derive(Debug)]
+pub struct DiskCache {
+    dir: PathBuf,
+}
+
+impl DiskCache {
+    pub fn new<P>(dir: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        Self { dir: dir.into() }
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+}
+
+impl Cache for DiskCache {
+    fn get(&self, key: &str) -> Option<CacheEntry> {
+        let path = self.path(key);
+        let json = match fs::read_to_string(&path) {
+            Ok(json) => json,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return None,
+            Err(e) => {
+                warn!("failed to read {}: {}", path.display(), e);
+                return None;
+            }
+        };
+        match serde_json::from_str(&json) {
+            Ok(entry) => Some(entry),
+            Err(e) => {
+                warn!("invalid cache entry {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    fn insert(&self, key: &str, entry: CacheEntry) {
+        let path = self.path(key);
+        let json = serde_json::to_string(&entry).expect("an entry can always be serialized");
+        if let Err(e) = fs::create_dir_all(&self.dir).and_then(|_| fs::write(&path, json)) {
+            warn!("failed to write {}: {}", path.display(), e);
+        }
+    }
+
+    fn remove(&self, key: &str) {
+        remove_file(&self.path(key));
+    }
+
+    fn clear(&self) {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return,
+            Err(e) => {
+                warn!("failed to read {}: {}", self.dir.display(), e);
+                return;
+            }
+        };
+        // The directory may be shared, e.g. with recorded fixtures
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let key = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_suffix(".json"));
+            let is_entry = || {
+                fs::read_to_string(&path)
+                    .ok()
+                    .and_then(|json| serde_json::from_str::<CacheEntry>(&json).ok())
+                    .is_some()
+            };
+            if key.is_some_and(is_key) && is_entry() {
+                remove_file(&path);
+            }
+        }
+    }
+}
+
+/// Whether `key` has the format of [`request_key`]: `{method}-{hash}`.
+fn is_key(key: &str) -> bool {
+    key.rsplit_once('-').is_some_and(|(method, hash)| {
+        !method.is_empty()
+            && method.chars().all(|c| c.is_ascii_alphanumeric())
+            && hash.len() == 16
+            && hash.chars().all(|c| c.is_ascii_hexdigit())
+    })
+}
+
+fn remove_file(path: &Path) {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => {
+            warn!("failed to remove {}: {}", path.display(), e)
+        }
+        _ => (),
+    }
+}
+
+/// A cache, with the TTL of each method.
+///
+/// The entries are keyed by the address and the account of the client as well,
+/// so that clients sharing a cache never see each other's responses.
+#[derive(Debug, Clone)]
+pub(crate) struct CacheLayer {
+    cache: Arc<dyn Cache>,
+    default_ttl: Duration,
+    ttls: HashMap<&'static str, Duration>,
+    address: String,
+    account: Option<String>,
+}
+
+impl CacheLayer {
+    pub(crate) fn new(
+        cache: Arc<dyn Cache>,
+        default_ttl: Duration,
+        ttls: HashMap<&'static str, Duration>,
+        address: String,
+    ) -> Self {
+        Self {
+            cache,
+            default_ttl,
+            ttls,
+            address,
+            account: None,
+        }
+    }
+
+    /// Sets the account the following responses belong to.
+    pub(crate) fn set_account(&mut self, account: Option<String>) {
+        self.account = account;
+    }
+
+    fn key(&self, method: &str, form: &[(String, String)]) -> String {
+        let scope = [
+            ("address".to_owned(), self.address.clone()),
+            (
+                "account".to_owned(),
+                self.account.clone().unwrap_or_default(),
+            ),
+        ];
+        request_key(method, &[&scope[..], form].concat())
+    }
+
+    fn ttl(&self, method: &str) -> Duration {
+        self.ttls.get(method).copied().unwrap_or(self.default_ttl)
+    }
+
+    /// Whether the responses to `method` are cached.
+    pub(crate) fn caches(&self, method: &str) -> bool {
+        !self.ttl(method).is_zero()
+    }
+
+    /// The response to `form`, if it is cached and still fresh.
+    pub(crate) fn get(&self, method: &str, form: &[(String, String)]) -> Option<HttpResponse> {
+        if !self.caches(method) {
+            return None;
+        }
+        let key = self.key(method, form);
+        let entry = self.cache.get(&key)?;
+        let age = entry.stored_at.elapsed().unwrap_or(Duration::ZERO);
+        if age < self.ttl(method) {
+            debug!("cache hit for {}", key);
+            Some(entry.response)
+        } else {
+            debug!("cache entry {} expired", key);
+            self.cache.remove(&key);
+            None
+        }
+    }
+
+    pub(crate) fn insert(&self, method: &str, form: &[(String, String)], response: HttpResponse) {
+        self.cache.insert(
+            &self.key(method, form),
+            CacheEntry {
+                stored_at: SystemTime::now(),
+                response,
+            },
+        );
+    }
+
+    pub(crate) fn remove(&self, method: &str, form: &[(String, String)]) {
+        self.cache.remove(&self.key(method, form));
+    }
+
+    pub(crate) fn clear(&self) {
+        self.cache.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(body: &str) -> CacheEntry {
+        CacheEntry {
+            stored_at: SystemTime::now(),
+            response: HttpResponse {
+                status: 200,
+                url: "https://example.com/calendar/Home/GetSideBarEvent".to_owned(),
+                content_type: Some("application/json".to_owned()),
+                body: body.to_owned(),
+            },
+        }
+    }
+
+    #[test]
+    fn evict_least_recently_used() {
+        let cache = MemoryCache::new(2);
+        cache.insert("a", entry("a"));
+        cache.insert("b", entry("b"));
+        cache.get("a").unwrap();
+        cache.insert("c", entry("c"));
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get("a").is_some());
+        assert!(cache.get("b").is_none());
+        assert!(cache.get("c").is_some());
+    }
+
+    #[test]
+    fn disk_cache() {
+        let dir = std::env::temp_dir().join(format!("cy-celcat-cache-{}", std::process::id()));
+        let cache = DiskCache::new(&dir);
+        assert!(cache.get("a").is_none());
+        cache.insert("a", entry("a"));
+        cache.insert("b", entry("b"));
+        assert_eq!(cache.get("a").unwrap().response.body, "a");
+        cache.remove("a");
+        assert!(cache.get("a").is_none());
+
+        let key = request_key("GetSideBarEvent", &[]);
+        cache.insert(&key, entry("b"));
+        // Files the cache didn't write, like a fixture with the same name format
+        let fixture = dir.join("GetCalendarData-0123456789abcdef.json");
+        fs::write(&fixture, r#"{"method":"GetCalendarData"}"#).unwrap();
+        let session = dir.join("session.json");
+        fs::write(&session, "{}").unwrap();
+        cache.clear();
+        assert!(cache.get(&key).is_none());
+        assert!(fixture.exists());
+        assert!(session.exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    fn layer(cache: Arc<dyn Cache>, address: &str, account: &str) -> CacheLayer {
+        let mut layer = CacheLayer::new(
+            cache,
+            Duration::from_secs(60),
+            vec![("GetCalendarData", Duration::ZERO)]
+                .into_iter()
+                .collect(),
+            address.to_owned(),
+        );
+        layer.set_account(Some(account.to_owned()));
+        layer
+    }
+
+    #[test]
+    fn ttl_per_method() {
+        let layer = layer(Arc::new(MemoryCache::new(10)), "https://example.com", "a");
+        let form = vec![("eventId".to_owned(), "1".to_owned())];
+        layer.insert("GetSideBarEvent", &form, entry("a").response);
+        assert!(layer.get("GetSideBarEvent", &form).is_some());
+        assert!(!layer.caches("GetCalendarData"));
+        layer.remove("GetSideBarEvent", &form);
+        assert!(layer.get("GetSideBarEvent", &form).is_none());
+    }
+
+    #[test]
+    fn separate_accounts() {
+        let cache: Arc<dyn Cache> = Arc::new(MemoryCache::new(10));
+        let a = layer(Arc::clone(&cache), "https://example.com", "a");
+        let b = layer(Arc::clone(&cache), "https://example.com", "b");
+        let other = layer(cache, "https://example.org", "a");
+        let form = vec![("eventId".to_owned(), "1".to_owned())];
+        a.insert("GetSideBarEvent", &form, entry("a").response);
+        assert!(a.get("GetSideBarEvent", &form).is_some());
+        assert!(b.get("GetSideBarEvent", &form).is_none());
+        assert!(other.get("GetSideBarEvent", &form).is_none());
+    }
+}
@@ -0,0 +1,76 @@
+use std::time::Duration;
+
+use tokio::{
+    sync::{Mutex, Semaphore, SemaphorePermit},
+    time::{self, Instant},
+};
+
+/// Limits the requests sent by a [`Celcat`](super::Celcat) client and its clones.
+#[derive(Debug, Default)]
+pub(crate) struct Limiter {
+    in_flight: Option<Semaphore>,
+    /// The minimal interval between two requests, and when the next one can be sent.
+    rate: Option<(Duration, Mutex<Instant>)>,
+}
+
+impl Limiter {
+    /// `max_in_flight` requests at the same time, and one request each `interval`.
+    pub(crate) fn new(max_in_flight: Option<usize>, interval: Option<Duration>) -> Self {
+        Self {
+            in_flight: max_in_flight.map(Semaphore::new),
+            rate: interval.map(|interval| (interval, Mutex::new(Instant::now()))),
+        }
+    }
+
+    /// Waits until a request can be sent.
+    ///
+    /// The returned permit must be held until the response is received.
+    pub(crate) async fn acquire(&self) -> Option<SemaphorePermit<'_>> {
+        let permit = match &self.in_flight {
+            Some(in_flight) => Some(
+                in_flight
+                    .acquire()
+                    .await
+                    .expect("the semaphore is never closed"),
+            ),
+            None => None,
+        };
+        if let Some((interval, next)) = &self.rate {
+            let slot = {
+                let mut next = next.lock().await;
+                let slot = (*next).max(Instant::now());
+                *next = slot + *interval;
+                slot
+            };
+            time::sleep_until(slot).await;
+        }
+        permit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn rate_limit() {
+        let limiter = Limiter::new(None, Some(Duration::from_millis(20)));
+        let start = Instant::now();
+        for _ in 0..5 {
+            limiter.acquire().await;
+        }
+        assert!(start.elapsed() >= Duration::from_millis(80));
+    }
+
+    #[tokio::test]
+    async fn max_in_flight() {
+        let limiter = Limiter::new(Some(2), None);
+        let a = limiter.acquire().await;
+        let _b = limiter.acquire().await;
+        assert!(time::timeout(Duration::from_millis(10), limiter.acquire())
+            .await
+            .is_err());
+        drop(a);
+        limiter.acquire().await;
+    }
+}
@@ -0,0 +1,1097 @@
+mod builder;
+pub mod cache;
+mod limit;
+pub mod record;
+mod retry;
+mod session;
+pub mod transport;
+
+use std::{
+    error::Error as StdError,
+    fmt, io,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use chrono::NaiveDateTime;
+use futures::{
+    stream::{self, StreamExt, TryStreamExt},
+    Stream,
+};
+use lazy_static::lazy_static;
+use log::{debug, info, warn};
+use regex::Regex;
+use serde::Serialize;
+use thiserror::Error;
+
+pub use self::{builder::CelcatBuilder, retry::RetryPolicy, session::Session};
+
+use self::{
+    cache::CacheLayer,
+    limit::Limiter,
+    record::{RecordingTransport, ReplayTransport},
+    transport::{HttpResponse, Transport},
+};
+use crate::{
+    entities::ResourceType,
+    fetchable::{
+        calendar::{CalView, CalendarData, CalendarDataRequest, ColourScheme, Course},
+        event::{Event, EventRequest},
+        resources::{Resource, ResourceList, ResourceListRequest},
+        Fetchable,
+    },
+    form,
+};
+
+/// Number of resources fetched at once by [`Celcat::resources`].
+const PAGE_SIZE: u64 = 50;
+
+/// Maximum number of events fetched at the same time by
+/// [`Celcat::fetch_calendar_with_details`], unless
+/// [`CelcatBuilder::max_concurrency`] is set.
+const DETAILS_CONCURRENCY: usize = 4;
+
+/// Maximum length of the body kept in [`FetchError::Decode`].
+const SNIPPET_LEN: usize = 200;
+
+#[derive(Error, Debug)]
+pub enum FetchError {
+    #[error("request error: {0}")]
+    Reqwest(#[source] reqwest::Error),
+    #[error("the request timed out")]
+    Timeout(#[source] reqwest::Error),
+    #[error("transport error: {0}")]
+    Transport(#[source] Box<dyn StdError + Send + Sync>),
+    #[error("{method} returned HTTP {status}")]
+    Status {
+        method: &'static str,
+        status: reqwest::StatusCode,
+    },
+    #[error("cannot decode the response of {method} at `{path}`: {source} (body: {snippet:?})")]
+    Decode {
+        method: &'static str,
+        path: String,
+        snippet: String,
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("{method} returned an HTML page instead of JSON")]
+    UnexpectedHtml { method: &'static str },
+    #[error("cannot access the fixture {}: {source}", path.display())]
+    Fixture {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error("cannot find the token")]
+    Token,
+    #[error("cannot encode the request: {0}")]
+    Encode(serde_json::Error),
+    #[error("the session has expired")]
+    SessionExpired,
+    #[error("invalid username or password")]
+    InvalidCredentials,
+    #[error("login failed ({status}): {message}")]
+    LoginFailed {
+        status: reqwest::StatusCode,
+        message: String,
+    },
+}
+
+impl From<reqwest::Error> for FetchError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            Self::Timeout(e)
+        } else {
+            Self::Reqwest(e)
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Credentials {
+    username: String,
+    password: String,
+}
+
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Credentials")
+            .field("username", &self.username)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Celcat {
+    transport: Arc<dyn Transport>,
+    address: String,
+    /// The antiforgery token matching the cookie, shared with the clones
+    /// as it changes when logging in again.
+    token: Arc<Mutex<String>>,
+    /// The account logged in, if known.
+    username: Option<String>,
+    credentials: Option<Credentials>,
+    auto_relogin: bool,
+    retry: RetryPolicy,
+    limiter: Arc<Limiter>,
+    /// Maximum number of events fetched at the same time by
+    /// [`Celcat::fetch_calendar_with_details`].
+    details_concurrency: usize,
+    cache: Option<CacheLayer>,
+    colour_scheme: ColourScheme,
+    cal_view: CalView,
+}
+
+impl Celcat {
+    pub async fn new<S>(address: S) -> Result<Self, FetchError>
+    where
+        S: AsRef<str>,
+    {
+        Self::builder(address.as_ref()).build().await
+    }
+
+    pub fn builder<S>(address: S) -> CelcatBuilder
+    where
+        S: Into<String>,
+    {
+        CelcatBuilder::new(address.into())
+    }
+
+    /// Creates a client sending its requests through `transport`.
+    ///
+    /// See the [`transport`] module.
+    pub async fn with_transport<S, T>(address: S, transport: T) -> Result<Self, FetchError>
+    where
+        S: AsRef<str>,
+        T: Transport + 'static,
+    {
+        Self::builder(address.as_ref())
+            .transport(transport)
+            .build()
+            .await
+    }
+
+    /// Creates a client which never touches the network, and serves the
+    /// fixtures recorded in `dir` (see [`Celcat::record_to`]) instead.
+    ///
+    /// See [`ReplayTransport`].
+    pub async fn replay<P>(dir: P) -> Result<Self, FetchError>
+    where
+        P: Into<PathBuf>,
+    {
+        Self::with_transport("replay://celcat", ReplayTransport::new(dir)).await
+    }
+
+    /// Records the response of every following [`Celcat::fetch`] in `dir`.
+    ///
+    /// See [`RecordingTransport`].
+    pub fn record_to<P>(&mut self, dir: P)
+    where
+        P: Into<PathBuf>,
+    {
+        self.transport = Arc::new(RecordingTransport::new(Arc::clone(&self.transport), dir));
+    }
+
+    /// Log in again with the credentials given to [`Celcat::login`]
+    /// when the session expires, instead of returning [`FetchError::SessionExpired`].
+    ///
+    /// It must be enabled before [`Celcat::login`], which only keeps the
+    /// credentials in memory when it is. Disabling it forgets them.
+    pub fn set_auto_relogin(&mut self, auto_relogin: bool) {
+        self.auto_relogin = auto_relogin;
+        if !auto_relogin {
+            self.credentials = None;
+        }
+    }
+
+    async fn fetch_token(transport: &dyn Transport, address: &str) -> Result<String, FetchError> {
+        info!("fetching celcat token");
+        let res = transport.get(&format!("{}/LdapLogin", address)).await?;
+        parse_token(&res.body)
+    }
+
+    /// Restores a session saved with [`Celcat::session`].
+    pub async fn from_session(session: Session) -> Result<Self, FetchError> {
+        Self::builder(session.address.clone())
+            .session(session)
+            .build()
+            .await
+    }
+
+    /// The current session, to restore it later with [`Celcat::from_session`].
+    ///
+    /// The cookies are only saved if the transport supports
+    /// [`Transport::cookies`], which is the case of the default one.
+    pub fn session(&self) -> Session {
+        Session {
+            address: self.address.clone(),
+            username: self.username.clone(),
+            token: self.token(),
+            cookies: self.transport.cookies(&self.address),
+        }
+    }
+
+    pub async fn login(&mut self, username: &str, password: &str) -> Result<(), FetchError> {
+        info!("fetching celcat federation ids");
+        self.send_login(&self.token(), username, password).await?;
+        self.username = Some(username.to_owned());
+        if let Some(cache) = &mut self.cache {
+            cache.set_account(self.username.clone());
+        }
+        if self.auto_relogin {
+            self.set_credentials(username, password);
+        }
+
+        Ok(())
+    }
+
+    /// Sets the credentials used to log in again when the session expires,
+    /// without logging in now. See [`Celcat::set_auto_relogin`].
+    ///
+    /// [`Celcat::login`] already sets them when auto-relogin is enabled.
+    pub fn set_credentials(&mut self, username: &str, password: &str) {
+        self.credentials = Some(Credentials {
+            username: username.to_owned(),
+            password: password.to_owned(),
+        });
+    }
+
+    async fn send_login(
+        &self,
+        token: &str,
+        username: &str,
+        password: &str,
+    ) -> Result<(), FetchError> {
+        let form = login_form(token, username, password)?;
+        let res = self
+            .transport
+            .post_form(&format!("{}/LdapLogin/Logon", self.address), &form)
+            .await?;
+        check_login(res)
+    }
+
+    async fn relogin(&self, credentials: &Credentials) -> Result<(), FetchError> {
+        info!("session expired, logging in again");
+        let token = Self::fetch_token(&*self.transport, &self.address).await?;
+        // The token is tied to the cookie set along with it
+        *self.token.lock().unwrap() = token.clone();
+        self.send_login(&token, &credentials.username, &credentials.password)
+            .await
+    }
+
+    fn token(&self) -> String {
+        self.token.lock().unwrap().clone()
+    }
+
+    /// Creates a request for the calendar of `federation_ids`, with the
+    /// colour scheme and the view configured with the [`CelcatBuilder`].
+    pub fn calendar_request<T>(
+        &self,
+        res_type: T,
+        federation_ids: Vec<T::Id>,
+        start: NaiveDateTime,
+        end: NaiveDateTime,
+    ) -> CalendarDataRequest<T>
+    where
+        T: ResourceType,
+    {
+        CalendarDataRequest {
+            cal_view: self.cal_view,
+            colour_scheme: self.colour_scheme,
+            ..CalendarDataRequest::new(res_type, federation_ids, start, end)
+        }
+    }
+
+    /// Sends `req`, retrying according to the [`RetryPolicy`].
+    ///
+    /// If a [`cache`](CelcatBuilder::cache) is configured, a fresh cached response
+    /// is returned instead, see [`Celcat::fetch_uncached`] to bypass it.
+    pub async fn fetch<F>(&self, req: F::Request) -> Result<F, FetchError>
+    where
+        F: Fetchable,
+    {
+        let form = form::to_pairs(&req).map_err(FetchError::Encode)?;
+        if let Some(res) = self
+            .cache
+            .as_ref()
+            .and_then(|cache| cache.get(F::METHOD_NAME, &form))
+        {
+            match check(res) {
+                Ok(res) => return Ok(res),
+                Err(e) => warn!("invalid cached response to {}: {}", F::METHOD_NAME, e),
+            }
+        }
+        self.fetch_form(&form).await
+    }
+
+    /// Like [`Celcat::fetch`], but always sends the request.
+    /// The response is still cached.
+    pub async fn fetch_uncached<F>(&self, req: F::Request) -> Result<F, FetchError>
+    where
+        F: Fetchable,
+    {
+        let form = form::to_pairs(&req).map_err(FetchError::Encode)?;
+        self.fetch_form(&form).await
+    }
+
+    /// Removes the cached response to `req`, if any.
+    pub fn invalidate<F>(&self, req: &F::Request) -> Result<(), FetchError>
+    where
+        F: Fetchable,
+    {
+        if let Some(cache) = &self.cache {
+            let form = form::to_pairs(req).map_err(FetchError::Encode)?;
+            cache.remove(F::METHOD_NAME, &form);
+        }
+        Ok(())
+    }
+
+    /// Removes every cached response.
+    pub fn clear_cache(&self) {
+        if let Some(cache) = &self.cache {
+            cache.clear();
+        }
+    }
+
+    async fn fetch_form<F>(&self, form: &[(String, String)]) -> Result<F, FetchError>
+    where
+        F: Fetchable,
+    {
+        let mut attempt = 1;
+        loop {
+            match self.fetch_once::<F>(form).await {
+                Err(e) if attempt < self.retry.max_attempts && self.retry.is_retryable(&e) => {
+                    let backoff = self.retry.backoff_for(attempt);
+                    warn!(
+                        "{} failed (attempt {}/{}), retrying in {:?}: {}",
+                        F::METHOD_NAME,
+                        attempt,
+                        self.retry.max_attempts,
+                        backoff,
+                        e
+                    );
+                    tokio::time::sleep(backoff).await;
+                    attempt += 1;
+                }
+                Err(e) => {
+                    if attempt > 1 {
+                        warn!("{} failed after {} attempts", F::METHOD_NAME, attempt);
+                    }
+                    return Err(e);
+                }
+                Ok(res) => {
+                    if attempt > 1 {
+                        info!("{} succeeded at attempt {}", F::METHOD_NAME, attempt);
+                    }
+                    return Ok(res);
+                }
+            }
+        }
+    }
+
+    async fn fetch_once<F>(&self, form: &[(String, String)]) -> Result<F, FetchError>
+    where
+        F: Fetchable,
+    {
+        let _permit = self.limiter.acquire().await;
+        match self.send::<F>(form).await {
+            Err(FetchError::SessionExpired) if self.auto_relogin => match &self.credentials {
+                Some(credentials) => {
+                    self.relogin(credentials).await?;
+                    self.send::<F>(form).await
+                }
+                None => {
+                    warn!("session expired, but no credentials to log in again");
+                    Err(FetchError::SessionExpired)
+                }
+            },
+            res => res,
+        }
+    }
+
+    async fn send<F>(&self, form: &[(String, String)]) -> Result<F, FetchError>
+    where
+        F: Fetchable,
+    {
+        let res = self
+            .transport
+            .post_form(&format!("{}/Home/{}", self.address, F::METHOD_NAME), form)
+            .await?;
+        // The login page Celcat redirects to when the session has expired
+        // comes with a new antiforgery cookie, and its token
+        if res.url.contains("/LdapLogin") {
+            if let Ok(token) = parse_token(&res.body) {
+                *self.token.lock().unwrap() = token;
+            }
+        }
+
+        match &self.cache {
+            Some(cache) if cache.caches(F::METHOD_NAME) => {
+                let value = check(res.clone())?;
+                cache.insert(F::METHOD_NAME, form, res);
+                Ok(value)
+            }
+            _ => check(res),
+        }
+    }
+
+    /// Lazily fetches every resource of type `res_type` matching `search_term`,
+    /// one page at a time.
+    pub fn resources<'a, R>(
+        &'a self,
+        res_type: R,
+        search_term: &str,
+    ) -> impl Stream<Item = Result<Resource<R>, FetchError>> + 'a
+    where
+        R: ResourceType + 'a,
+    {
+        let search_term = search_term.to_owned();
+        stream::try_unfold(Some(1), move |page_number| {
+            let req = page_number.map(|page_number| ResourceListRequest {
+                my_resources: false,
+                search_term: search_term.clone(),
+                page_size: PAGE_SIZE,
+                page_number,
+                res_type: res_type.clone(),
+            });
+            async move {
+                let req = match req {
+                    Some(req) => req,
+                    None => return Ok(None),
+                };
+                let page = req.page_number;
+                debug!("fetching page {} of resources", page);
+                let list: ResourceList<R> = self.fetch(req).await?;
+                let next = if !list.results.is_empty() && page * PAGE_SIZE < list.total {
+                    Some(page + 1)
+                } else {
+                    None
+                };
+                let results = list.results.into_iter().map(Ok::<_, FetchError>);
+                Ok::<_, FetchError>(Some((stream::iter(results), next)))
+            }
+        })
+        .try_flatten()
+    }
+
+    /// Fetches every resource of type `res_type` matching `search_term`.
+    ///
+    /// See [`Celcat::resources`].
+    pub async fn collect_all<R>(
+        &self,
+        res_type: R,
+        search_term: &str,
+    ) -> Result<Vec<Resource<R>>, FetchError>
+    where
+        R: ResourceType,
+    {
+        self.resources(res_type, search_term).try_collect().await
+    }
+
+    /// Fetches a calendar, and the [`Event`] of each of its courses.
+    ///
+    /// The events are fetched [`max_concurrency`](CelcatBuilder::max_concurrency)
+    /// at a time, or 4 if it isn't set, and within the rate limit. Only fetching the calendar itself can fail
+    /// the whole batch: the error of an event is returned with its course.
+    pub async fn fetch_calendar_with_details<T>(
+        &self,
+        req: CalendarDataRequest<T>,
+    ) -> Result<Vec<(Course, Result<Event, FetchError>)>, FetchError>
+    where
+        T: ResourceType,
+    {
+        let data: CalendarData<T> = self.fetch(req).await?;
+        let events: Vec<_> = stream::iter(&data.courses)
+            .map(|course| {
+                self.fetch::<Event>(EventRequest {
+                    event_id: course.id.clone(),
+                })
+            })
+            .buffered(self.details_concurrency)
+            .collect()
+            .await;
+        Ok(data.courses.into_iter().zip(events).collect())
+    }
+}
+
+pub(crate) fn parse_token(body: &str) -> Result<String, FetchError> {
+    lazy_static! {
+        static ref RE: Regex =
+            Regex::new(r#"<input name="__RequestVerificationToken".*?value="([^"]+)""#).unwrap();
+    }
+    if let Some(token) = RE.captures(body).and_then(|caps| caps.get(1)) {
+        Ok(token.as_str().to_owned())
+    } else {
+        Err(FetchError::Token)
+    }
+}
+
+pub(crate) fn login_form(
+    token: &str,
+    username: &str,
+    password: &str,
+) -> Result<Vec<(String, String)>, FetchError> {
+    #[derive(Serialize)]
+    struct Form<'a> {
+        #[serde(rename = "Name")]
+        username: &'a str,
+        #[serde(rename = "Password")]
+        password: &'a str,
+        #[serde(rename = "__RequestVerificationToken")]
+        token: &'a str,
+    }
+
+    let form = Form {
+        username,
+        password,
+        token,
+    };
+    form::to_pairs(&form).map_err(FetchError::Encode)
+}
+
+pub(crate) fn check_login(res: HttpResponse) -> Result<(), FetchError> {
+    let status = status_code(res.status);
+    // On success, Celcat redirects to the calendar,
+    // otherwise it shows the login form again
+    let redirected_to_login = res.url.contains("/LdapLogin");
+    let body = res.body;
+    let message = login_error_message(&body);
+
+    if !status.is_success() {
+        Err(FetchError::LoginFailed {
+            status,
+            message: message
+                .or_else(|| status.canonical_reason().map(str::to_owned))
+                .unwrap_or_default(),
+        })
+    } else if redirected_to_login || body.contains(r#"name="Password""#) {
+        match message {
+            Some(message) if !is_bad_credentials(&message) => {
+                Err(FetchError::LoginFailed { status, message })
+            }
+            _ => Err(FetchError::InvalidCredentials),
+        }
+    } else {
+        Ok(())
+    }
+}
+
+fn status_code(status: u16) -> reqwest::StatusCode {
+    reqwest::StatusCode::from_u16(status).unwrap_or(reqwest::StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+pub(crate) fn check<F>(res: HttpResponse) -> Result<F, FetchError>
+where
+    F: Fetchable,
+{
+    let status = status_code(res.status);
+    let html = res.is_html();
+    // When the session has expired, Celcat redirects to the login page
+    let login_page = res.url.contains("/LdapLogin");
+
+    if login_page || (html && res.body.contains(r#"name="Password""#)) {
+        Err(FetchError::SessionExpired)
+    } else if !status.is_success() {
+        Err(FetchError::Status {
+            method: F::METHOD_NAME,
+            status,
+        })
+    } else if html {
+        Err(FetchError::UnexpectedHtml {
+            method: F::METHOD_NAME,
+        })
+    } else {
+        decode(&res.body)
+    }
+}
+
+fn decode<F>(body: &str) -> Result<F, FetchError>
+where
+    F: Fetchable,
+{
+    let de = &mut serde_json::Deserializer::from_str(body);
+    serde_path_to_error::deserialize(de).map_err(|e| {
+        let path = e.path().to_string();
+        let snippet = match body.char_indices().nth(SNIPPET_LEN) {
+            Some((i, _)) => format!("{}…", &body[..i]),
+            None => body.to_owned(),
+        };
+        FetchError::Decode {
+            method: F::METHOD_NAME,
+            path,
+            snippet,
+            source: e.into_inner(),
+        }
+    })
+}
+
+/// Whether the error shown by the login page is about a wrong username or password,
+/// rather than a locked account or a server error.
+fn is_bad_credentials(message: &str) -> bool {
+    let message = message.to_lowercase();
+    (message.contains("incorrect") || message.contains("invalid"))
+        && (message.contains("mot de passe") || message.contains("password"))
+}
+
+/// Extracts the error shown by the login page.
+fn login_error_message(body: &str) -> Option<String> {
+    lazy_static! {
+        static ref RE: Regex = Regex::new(
+            r#"(?s)class="(?:validation-summary-errors|field-validation-error)[^"]*"[^>]*>\s*(?:<ul>\s*<li[^>]*>)?([^<]+)"#
+        )
+        .unwrap();
+    }
+    RE.captures(body)
+        .and_then(|caps| caps.get(1))
+        .map(|m| m.as_str().trim().to_owned())
+        .filter(|m| !m.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        entities::{Room, RoomId, Student, StudentId},
+        fetchable::calendar::tests::course_json,
+        mock::{Fixtures, MockServer},
+        CourseId,
+    };
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn event() -> serde_json::Value {
+        json!({
+            "federationId": null,
+            "entityType": 0,
+            "elements": []
+        })
+    }
+
+    async fn server() -> MockServer {
+        let mut fixtures = Fixtures::default();
+        fixtures.events.insert("1".to_owned(), event());
+        fixtures.resources = (0..120)
+            .map(|i| json!({ "id": i.to_string(), "text": format!("ROOM {}", i), "dept": "CY" }))
+            .collect();
+        MockServer::start(fixtures).await.unwrap()
+    }
+
+    fn event_request(id: &str) -> EventRequest {
+        EventRequest {
+            event_id: CourseId(id.to_owned()),
+        }
+    }
+
+    #[tokio::test]
+    async fn login_and_fetch() {
+        let server = server().await;
+        let mut celcat = Celcat::new(server.address()).await.unwrap();
+        celcat.login("username", "password").await.unwrap();
+        celcat.fetch::<Event>(event_request("1")).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn invalid_credentials() {
+        let server = server().await;
+        let mut celcat = Celcat::new(server.address()).await.unwrap();
+        assert!(matches!(
+            celcat.login("username", "wrong").await,
+            Err(FetchError::InvalidCredentials)
+        ));
+    }
+
+    #[tokio::test]
+    async fn session_expired() {
+        let server = server().await;
+        let mut celcat = Celcat::new(server.address()).await.unwrap();
+        celcat.login("username", "password").await.unwrap();
+        assert!(celcat.credentials.is_none());
+        server.expire_sessions();
+        assert!(matches!(
+            celcat.fetch::<Event>(event_request("1")).await,
+            Err(FetchError::SessionExpired)
+        ));
+
+        celcat.set_auto_relogin(true);
+        celcat.login("username", "password").await.unwrap();
+        server.expire_sessions();
+        celcat.fetch::<Event>(event_request("1")).await.unwrap();
+
+        celcat.set_auto_relogin(false);
+        assert!(celcat.credentials.is_none());
+    }
+
+    #[tokio::test]
+    async fn status_error() {
+        let server = server().await;
+        let mut celcat = Celcat::new(server.address()).await.unwrap();
+        celcat.login("username", "password").await.unwrap();
+        server.set_status("GetSideBarEvent", Some(reqwest::StatusCode::BAD_GATEWAY));
+        assert!(matches!(
+            celcat.fetch::<Event>(event_request("1")).await,
+            Err(FetchError::Status {
+                method: "GetSideBarEvent",
+                status: reqwest::StatusCode::BAD_GATEWAY,
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn custom_transport() {
+        #[derive(Debug)]
+        struct Counting(reqwest::Client, Arc<AtomicUsize>);
+
+        impl Transport for Counting {
+            fn get<'a>(
+                &'a self,
+                url: &'a str,
+            ) -> futures::future::BoxFuture<'a, Result<HttpResponse, FetchError>> {
+                self.1.fetch_add(1, Ordering::SeqCst);
+                Transport::get(&self.0, url)
+            }
+
+            fn post_form<'a>(
+                &'a self,
+                url: &'a str,
+                form: &'a [(String, String)],
+            ) -> futures::future::BoxFuture<'a, Result<HttpResponse, FetchError>> {
+                self.1.fetch_add(1, Ordering::SeqCst);
+                self.0.post_form(url, form)
+            }
+        }
+
+        let server = server().await;
+        let client = reqwest::Client::builder()
+            .cookie_store(true)
+            .build()
+            .unwrap();
+        let count = Arc::new(AtomicUsize::new(0));
+        let transport = Counting(client, Arc::clone(&count));
+        let mut celcat = Celcat::with_transport(server.address(), transport)
+            .await
+            .unwrap();
+        celcat.login("username", "password").await.unwrap();
+        celcat.fetch::<Event>(event_request("1")).await.unwrap();
+        assert_eq!(count.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn record_and_replay() {
+        let dir = std::env::temp_dir().join(format!("cy-celcat-fixtures-{}", std::process::id()));
+        let server = server().await;
+        let mut celcat = Celcat::new(server.address()).await.unwrap();
+        celcat.login("username", "password").await.unwrap();
+        celcat.record_to(&dir);
+        let recorded = celcat.fetch::<Event>(event_request("1")).await.unwrap();
+        drop(server);
+
+        let mut celcat = Celcat::replay(&dir).await.unwrap();
+        celcat.login("username", "password").await.unwrap();
+        assert_eq!(
+            celcat.fetch::<Event>(event_request("1")).await.unwrap(),
+            recorded
+        );
+        assert!(matches!(
+            celcat.fetch::<Event>(event_request("2")).await,
+            Err(FetchError::Fixture { .. })
+        ));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn record_only_successes() {
+        let dir = std::env::temp_dir().join(format!("cy-celcat-expired-{}", std::process::id()));
+        let server = server().await;
+        let mut celcat = Celcat::new(server.address()).await.unwrap();
+        celcat.login("username", "password").await.unwrap();
+        celcat.record_to(&dir);
+        celcat.fetch::<Event>(event_request("1")).await.unwrap();
+        let path = record::fixture_path(
+            &dir,
+            "GetSideBarEvent",
+            &[("eventId".to_owned(), "1".to_owned())],
+        );
+        let fixture = std::fs::read_to_string(&path).unwrap();
+
+        assert!(celcat.fetch::<Event>(event_request("2")).await.is_err());
+        server.expire_sessions();
+        assert!(matches!(
+            celcat.fetch::<Event>(event_request("1")).await,
+            Err(FetchError::SessionExpired)
+        ));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), fixture);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn retry_transient_errors() {
+        let server = server().await;
+        let mut celcat = Celcat::builder(server.address())
+            .retry(RetryPolicy::new(3, std::time::Duration::from_millis(1)))
+            .max_concurrency(1)
+            .build()
+            .await
+            .unwrap();
+        celcat.login("username", "password").await.unwrap();
+        let requests = server.requests().len();
+
+        server.set_status(
+            "GetSideBarEvent",
+            Some(reqwest::StatusCode::SERVICE_UNAVAILABLE),
+        );
+        celcat.fetch::<Event>(event_request("1")).await.unwrap_err();
+        assert_eq!(server.requests().len(), requests + 3);
+
+        server.set_status("GetSideBarEvent", Some(reqwest::StatusCode::NOT_FOUND));
+        celcat.fetch::<Event>(event_request("1")).await.unwrap_err();
+        assert_eq!(server.requests().len(), requests + 4);
+    }
+
+    #[tokio::test]
+    async fn calendar_with_details() {
+        let mut fixtures = Fixtures {
+            calendar: vec![course_json("1"), course_json("2")],
+            ..Fixtures::default()
+        };
+        fixtures.events.insert("1".to_owned(), event());
+        let server = MockServer::start(fixtures).await.unwrap();
+        let mut celcat = Celcat::builder(server.address())
+            .max_concurrency(2)
+            .build()
+            .await
+            .unwrap();
+        celcat.login("username", "password").await.unwrap();
+        assert_eq!(celcat.details_concurrency, 2);
+        assert_eq!(
+            Celcat::new(server.address())
+                .await
+                .unwrap()
+                .details_concurrency,
+            DETAILS_CONCURRENCY
+        );
+
+        let start = chrono::NaiveDate::from_ymd(2021, 9, 20).and_hms(0, 0, 0);
+        let req = celcat.calendar_request(
+            Student,
+            vec![StudentId("1".to_owned())],
+            start,
+            start + chrono::Duration::weeks(1),
+        );
+        let courses = celcat.fetch_calendar_with_details(req).await.unwrap();
+        assert_eq!(courses.len(), 2);
+        assert_eq!(courses[0].0.id, CourseId("1".to_owned()));
+        assert!(courses[0].1.is_ok());
+        assert_eq!(courses[1].0.id, CourseId("2".to_owned()));
+        assert!(matches!(courses[1].1, Err(FetchError::Status { .. })));
+    }
+
+    #[tokio::test]
+    async fn cache_responses() {
+        let server = server().await;
+        let mut celcat = Celcat::builder(server.address())
+            .cache(
+                cache::MemoryCache::new(10),
+                std::time::Duration::from_secs(60),
+            )
+            .cache_ttl::<ResourceList<Room>>(std::time::Duration::ZERO)
+            .build()
+            .await
+            .unwrap();
+        celcat.login("username", "password").await.unwrap();
+        let requests = server.requests().len();
+
+        celcat.fetch::<Event>(event_request("1")).await.unwrap();
+        celcat.fetch::<Event>(event_request("1")).await.unwrap();
+        assert_eq!(server.requests().len(), requests + 1);
+
+        celcat
+            .fetch_uncached::<Event>(event_request("1"))
+            .await
+            .unwrap();
+        assert_eq!(server.requests().len(), requests + 2);
+
+        celcat.invalidate::<Event>(&event_request("1")).unwrap();
+        celcat.fetch::<Event>(event_request("1")).await.unwrap();
+        assert_eq!(server.requests().len(), requests + 3);
+
+        celcat.collect_all(Room, "room 1").await.unwrap();
+        celcat.collect_all(Room, "room 1").await.unwrap();
+        assert_eq!(server.requests().len(), requests + 5);
+    }
+
+    #[tokio::test]
+    async fn shared_disk_cache() {
+        let dir = std::env::temp_dir().join(format!("cy-celcat-shared-{}", std::process::id()));
+        let client = |address: &str| {
+            Celcat::builder(address).cache(
+                cache::DiskCache::new(&dir),
+                std::time::Duration::from_secs(60),
+            )
+        };
+        let server = server().await;
+        let mut fixtures = Fixtures {
+            username: "other".to_owned(),
+            ..Fixtures::default()
+        };
+        fixtures.events.insert("1".to_owned(), event());
+        let other_server = MockServer::start(fixtures).await.unwrap();
+
+        let mut celcat = client(server.address()).build().await.unwrap();
+        celcat.login("username", "password").await.unwrap();
+        celcat.fetch::<Event>(event_request("1")).await.unwrap();
+
+        let mut other = client(other_server.address()).build().await.unwrap();
+        other.login("other", "password").await.unwrap();
+        let requests = other_server.requests().len();
+        other.fetch::<Event>(event_request("1")).await.unwrap();
+        assert_eq!(other_server.requests().len(), requests + 1);
+
+        // A restored session keeps using the entries of its account
+        let restored = client(server.address())
+            .session(celcat.session())
+            .build()
+            .await
+            .unwrap();
+        let requests = server.requests().len();
+        restored.fetch::<Event>(event_request("1")).await.unwrap();
+        assert_eq!(server.requests().len(), requests);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "the concurrency limit must be positive")]
+    fn zero_concurrency() {
+        Celcat::builder("http://localhost").max_concurrency(0);
+    }
+
+    #[test]
+    #[should_panic(expected = "the rate limit is too small")]
+    fn tiny_rate_limit() {
+        Celcat::builder("http://localhost").rate_limit(f64::MIN_POSITIVE);
+    }
+
+    #[tokio::test]
+    async fn restore_session() {
+        let server = server().await;
+        let mut celcat = Celcat::new(server.address()).await.unwrap();
+        celcat.login("username", "password").await.unwrap();
+        let session = celcat.session();
+        assert_eq!(session.token, "mock-token");
+        assert!(session
+            .cookies
+            .contains(&"CelcatSession=session-1".to_owned()));
+        drop(celcat);
+
+        let session = serde_json::from_str(&serde_json::to_string(&session).unwrap()).unwrap();
+        let celcat = Celcat::from_session(session).await.unwrap();
+        celcat.fetch::<Event>(event_request("1")).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn session_after_relogin() {
+        let server = server().await;
+        let mut celcat = Celcat::new(server.address()).await.unwrap();
+        celcat.set_auto_relogin(true);
+        celcat.login("username", "password").await.unwrap();
+        server.expire_sessions();
+        celcat.fetch::<Event>(event_request("1")).await.unwrap();
+
+        let session = celcat.session();
+        assert_ne!(session.token, "mock-token");
+        let mut celcat = Celcat::from_session(session).await.unwrap();
+        server.expire_sessions();
+        celcat.login("username", "password").await.unwrap();
+        celcat.fetch::<Event>(event_request("1")).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn paginate_resources() {
+        let server = server().await;
+        let mut celcat = Celcat::new(server.address()).await.unwrap();
+        celcat.login("username", "password").await.unwrap();
+        let rooms = celcat.collect_all(Room, "").await.unwrap();
+        assert_eq!(rooms.len(), 120);
+        assert_eq!(rooms[119].id, RoomId("119".to_owned()));
+        let rooms = celcat.collect_all(Room, "room 11").await.unwrap();
+        assert_eq!(rooms.len(), 11);
+    }
+
+    #[test]
+    fn decode_error() {
+        let body = r#"{"federationId": null, "entityType": 0, "elements": [{"label": 3}]}"#;
+        match decode::<Event>(body).unwrap_err() {
+            FetchError::Decode {
+                method,
+                path,
+                snippet,
+                ..
+            } => {
+                assert_eq!(method, "GetSideBarEvent");
+                assert_eq!(path, "elements");
+                assert_eq!(snippet, body);
+            }
+            e => panic!("unexpected error: {}", e),
+        }
+
+        let body = format!("[{}]", "1, ".repeat(100));
+        match decode::<Event>(&body).unwrap_err() {
+            FetchError::Decode { path, snippet, .. } => {
+                assert_eq!(path, "[0]");
+                assert_eq!(snippet.chars().count(), SNIPPET_LEN + 1);
+            }
+            e => panic!("unexpected error: {}", e),
+        }
+    }
+
+    #[test]
+    fn extract_login_error_message() {
+        assert_eq!(
+            login_error_message(
+                r#"<form><div class="validation-summary-errors text-danger"><ul>
+                    <li>Nom d'utilisateur ou mot de passe incorrect</li>
+                </ul></div></form>"#
+            )
+            .as_deref(),
+            Some("Nom d'utilisateur ou mot de passe incorrect")
+        );
+        assert_eq!(
+            login_error_message(
+                r#"<span class="field-validation-error" data-valmsg-for="Name">Required</span>"#
+            )
+            .as_deref(),
+            Some("Required")
+        );
+        assert_eq!(login_error_message("<form></form>"), None);
+    }
+
+    #[test]
+    fn login_errors() {
+        let page = |error: &str| HttpResponse {
+            status: 200,
+            url: "https://example.com/calendar/LdapLogin/Logon".to_owned(),
+            content_type: Some("text/html; charset=utf-8".to_owned()),
+            body: format!(
+                r#"<form><div class="validation-summary-errors"><ul><li>{}</li></ul></div>
+                <input name="Password" type="password" /></form>"#,
+                error
+            ),
+        };
+        assert!(matches!(
+            check_login(page("Identifiant ou mot de passe incorrect")),
+            Err(FetchError::InvalidCredentials)
+        ));
+        assert!(matches!(
+            check_login(page("")),
+            Err(FetchError::InvalidCredentials)
+        ));
+        match check_login(page("Votre compte est verrouillé")) {
+            Err(FetchError::LoginFailed { status, message }) => {
+                assert_eq!(status, reqwest::StatusCode::OK);
+                assert_eq!(message, "Votre compte est verrouillé");
+            }
+            res => panic!("unexpected result: {:?}", res),
+        }
+    }
+}
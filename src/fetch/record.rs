@@ -0,0 +1,231 @@
+//! Recording and replay of Celcat responses.
+//!
+//! [`RecordingTransport`] stores the response to each
+//! [`Fetchable`](crate::fetchable::Fetchable) request as a JSON [`Fixture`],
+//! named after the method and a hash of the request, and [`ReplayTransport`]
+//! serves them back.
+//! Only the successful responses are recorded: never the login requests or
+//! pages, so the fixtures don't contain credentials or tokens.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use futures::future::BoxFuture;
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    transport::{HttpResponse, Transport},
+    FetchError,
+};
+
+/// A recorded response.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Fixture {
+    /// The [`Fetchable::METHOD_NAME`](crate::fetchable::Fetchable::METHOD_NAME).
+    pub method: String,
+    /// The form sent to Celcat.
+    pub request: Vec<(String, String)>,
+    pub response: HttpResponse,
+}
+
+/// The file in which the response to the request `form` to `method` is stored.
+pub fn fixture_path(dir: &Path, method: &str, form: &[(String, String)]) -> PathBuf {
+    dir.join(format!("{}.json", request_key(method, form)))
+}
+
+/// A key identifying the request `form` to `method`, usable as a file name.
+pub(crate) fn request_key(method: &str, form: &[(String, String)]) -> String {
+    let key = serde_json::to_string(form).expect("a form can always be serialized");
+    format!("{}-{:016x}", method, fnv1a(key.as_bytes()))
+}
+
+/// A transport recording the responses of another one in a directory.
+#[derive(Debug)]
+pub struct RecordingTransport {
+    inner: Arc<dyn Transport>,
+    dir: PathBuf,
+}
+
+impl RecordingTransport {
+    pub fn new<P>(inner: Arc<dyn Transport>, dir: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        Self {
+            inner,
+            dir: dir.into(),
+        }
+    }
+}
+
+impl Transport for RecordingTransport {
+    fn get<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<HttpResponse, FetchError>> {
+        self.inner.get(url)
+    }
+
+    fn post_form<'a>(
+        &'a self,
+        url: &'a str,
+        form: &'a [(String, String)],
+    ) -> BoxFuture<'a, Result<HttpResponse, FetchError>> {
+        Box::pin(async move {
+            let res = self.inner.post_form(url, form).await?;
+            // An error, or the login page of an expired session,
+            // would replace a good fixture
+            let success = (200..300).contains(&res.status) && !res.url.contains("/LdapLogin");
+            if let Some(method) = method_name(url).filter(|_| success) {
+                save(
+                    &self.dir,
+                    &Fixture {
+                        method: method.to_owned(),
+                        request: form.to_owned(),
+                        response: res.clone(),
+                    },
+                )?;
+            }
+            Ok(res)
+        })
+    }
+
+    fn cookies(&self, url: &str) -> Vec<String> {
+        self.inner.cookies(url)
+    }
+
+    fn set_cookies(&self, url: &str, cookies: &[String]) {
+        self.inner.set_cookies(url, cookies)
+    }
+}
+
+/// A transport serving the fixtures recorded by a [`RecordingTransport`],
+/// without touching the network.
+///
+/// Any login succeeds, and a request which wasn't recorded fails with
+/// [`FetchError::Fixture`].
+#[derive(Debug)]
+pub struct ReplayTransport {
+    dir: PathBuf,
+}
+
+impl ReplayTransport {
+    pub fn new<P>(dir: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        Self { dir: dir.into() }
+    }
+}
+
+impl Transport for ReplayTransport {
+    fn get<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<HttpResponse, FetchError>> {
+        Box::pin(async move {
+            Ok(if url.ends_with("/LdapLogin") {
+                html(
+                    url,
+                    r#"<input name="__RequestVerificationToken" type="hidden" value="replay" />"#,
+                )
+            } else {
+                HttpResponse {
+                    status: 404,
+                    ..html(url, "")
+                }
+            })
+        })
+    }
+
+    fn post_form<'a>(
+        &'a self,
+        url: &'a str,
+        form: &'a [(String, String)],
+    ) -> BoxFuture<'a, Result<HttpResponse, FetchError>> {
+        Box::pin(async move {
+            match method_name(url) {
+                Some(method) => load(&self.dir, method, form).map(|f| f.response),
+                // Celcat redirects to the calendar after a successful login
+                None if url.ends_with("/LdapLogin/Logon") => {
+                    Ok(html(&url.replace("/LdapLogin/Logon", "/Home"), ""))
+                }
+                None => Ok(HttpResponse {
+                    status: 404,
+                    ..html(url, "")
+                }),
+            }
+        })
+    }
+}
+
+fn html(url: &str, body: &str) -> HttpResponse {
+    HttpResponse {
+        status: 200,
+        url: url.to_owned(),
+        content_type: Some("text/html; charset=utf-8".to_owned()),
+        body: body.to_owned(),
+    }
+}
+
+/// The method name of a `/Home/{method}` URL.
+fn method_name(url: &str) -> Option<&str> {
+    url.rsplit_once("/Home/").map(|(_, method)| method)
+}
+
+fn save(dir: &Path, fixture: &Fixture) -> Result<(), FetchError> {
+    let path = fixture_path(dir, &fixture.method, &fixture.request);
+    debug!("recording {}", path.display());
+    let json = serde_json::to_string_pretty(fixture).expect("a fixture can always be serialized");
+    fs::create_dir_all(dir)
+        .and_then(|_| fs::write(&path, json))
+        .map_err(|source| FetchError::Fixture { path, source })
+}
+
+fn load(dir: &Path, method: &str, form: &[(String, String)]) -> Result<Fixture, FetchError> {
+    let path = fixture_path(dir, method, form);
+    debug!("replaying {}", path.display());
+    fs::read_to_string(&path)
+        .and_then(|json| {
+            serde_json::from_str(&json).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+        })
+        .map_err(|source| FetchError::Fixture { path, source })
+}
+
+/// 64-bit FNV-1a, which unlike the standard library hashers is stable
+/// across Rust versions and platforms.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn form(id: &str) -> Vec<(String, String)> {
+        vec![("eventId".to_owned(), id.to_owned())]
+    }
+
+    #[test]
+    fn stable_fixture_path() {
+        assert_eq!(fnv1a(b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a(b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(
+            fixture_path(Path::new("fixtures"), "GetSideBarEvent", &form("1")),
+            fixture_path(Path::new("fixtures"), "GetSideBarEvent", &form("1")),
+        );
+        assert_ne!(
+            fixture_path(Path::new("fixtures"), "GetSideBarEvent", &form("1")),
+            fixture_path(Path::new("fixtures"), "GetSideBarEvent", &form("2")),
+        );
+    }
+
+    #[test]
+    fn extract_method_name() {
+        assert_eq!(
+            method_name("https://example.com/calendar/Home/GetCalendarData"),
+            Some("GetCalendarData")
+        );
+        assert_eq!(method_name("https://example.com/calendar/LdapLogin"), None);
+    }
+}
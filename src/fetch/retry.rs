@@ -0,0 +1,134 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    time::Duration,
+};
+
+use super::FetchError;
+
+/// When and how [`Celcat::fetch`](super::Celcat::fetch) retries a failed request.
+///
+/// Every [`Fetchable`](crate::fetchable::Fetchable) only reads data, so any
+/// request can be retried; by default only on transient errors,
+/// see [`RetryPolicy::is_transient`].
+/// The time to wait grows exponentially after each attempt.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first one.
+    pub max_attempts: u32,
+    /// Time to wait after the first failed attempt.
+    pub backoff: Duration,
+    /// Factor by which the time to wait grows after each attempt.
+    pub multiplier: f64,
+    pub max_backoff: Duration,
+    /// Waits a random time between half and all of the backoff,
+    /// so that clients failing together don't retry together.
+    pub jitter: bool,
+    /// Whether an error is worth retrying.
+    pub retryable: fn(&FetchError) -> bool,
+}
+
+impl RetryPolicy {
+    /// Never retries.
+    pub fn none() -> Self {
+        Self::new(1, Duration::ZERO)
+    }
+
+    /// Retries up to `max_attempts` in total, waiting `backoff` after the first
+    /// failure, and then twice as long after each one, up to 30 seconds, with jitter.
+    pub fn new(max_attempts: u32, backoff: Duration) -> Self {
+        Self {
+            max_attempts,
+            backoff,
+            multiplier: 2.0,
+            max_backoff: Duration::from_secs(30),
+            jitter: true,
+            retryable: Self::is_transient,
+        }
+    }
+
+    pub fn multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    pub fn max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    pub fn jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    pub fn retryable(mut self, retryable: fn(&FetchError) -> bool) -> Self {
+        self.retryable = retryable;
+        self
+    }
+
+    /// Timeouts, connection errors, and the 502, 503 and 504 HTTP statuses.
+    pub fn is_transient(e: &FetchError) -> bool {
+        match e {
+            FetchError::Timeout(_) => true,
+            FetchError::Reqwest(e) => e.is_connect(),
+            FetchError::Status { status, .. } => matches!(status.as_u16(), 502..=504),
+            _ => false,
+        }
+    }
+
+    pub fn is_retryable(&self, e: &FetchError) -> bool {
+        (self.retryable)(e)
+    }
+
+    /// Time to wait after the failed attempt number `attempt`, starting at 1.
+    pub fn backoff_for(&self, attempt: u32) -> Duration {
+        let exp = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let backoff = self.backoff.as_secs_f64() * self.multiplier.powi(exp);
+        let backoff = if backoff < self.max_backoff.as_secs_f64() {
+            Duration::from_secs_f64(backoff.max(0.0))
+        } else {
+            self.max_backoff
+        };
+        if self.jitter {
+            backoff.mul_f64(0.5 + random() * 0.5)
+        } else {
+            backoff
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+/// A random number in `[0, 1)`, good enough for jitter without depending on `rand`.
+fn random() -> f64 {
+    let bits = RandomState::new().build_hasher().finish() >> 11;
+    bits as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exponential_backoff() {
+        let policy = RetryPolicy::new(5, Duration::from_millis(100))
+            .max_backoff(Duration::from_millis(300))
+            .jitter(false);
+        assert_eq!(policy.backoff_for(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff_for(2), Duration::from_millis(200));
+        assert_eq!(policy.backoff_for(3), Duration::from_millis(300));
+        assert_eq!(policy.backoff_for(100), Duration::from_millis(300));
+
+        let policy = policy.jitter(true);
+        for attempt in 1..5 {
+            let backoff = policy.backoff_for(attempt);
+            assert!(backoff >= Duration::from_millis(50));
+            assert!(backoff <= Duration::from_millis(300));
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// A logged in session, saved with [`Celcat::session`](super::Celcat::session)
+/// to be restored later with [`Celcat::from_session`](super::Celcat::from_session),
+/// without logging in again.
+///
+/// The cookies give access to the account until the session expires,
+/// so it must be stored as carefully as a password.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+    pub address: String,
+    /// The account logged in, which scopes the [`cache`](super::cache).
+    #[serde(default)]
+    pub username: Option<String>,
+    pub token: String,
+    /// The cookies, as `name=value` strings.
+    pub cookies: Vec<String>,
+}
+
+/// Only shows the names of the cookies.
+impl fmt::Debug for Session {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let cookies: Vec<_> = self
+            .cookies
+            .iter()
+            .map(|c| c.split_once('=').map_or(c.as_str(), |(name, _)| name))
+            .collect();
+        f.debug_struct("Session")
+            .field("address", &self.address)
+            .field("username", &self.username)
+            .field("cookies", &cookies)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redacted_debug() {
+        let session = Session {
+            address: "https://example.com/calendar".to_owned(),
+            username: Some("username".to_owned()),
+            token: "secret-token".to_owned(),
+            cookies: vec!["CelcatSession=secret-session".to_owned()],
+        };
+        let debug = format!("{:?}", session);
+        assert!(debug.contains("CelcatSession"));
+        assert!(!debug.contains("secret"));
+    }
+}
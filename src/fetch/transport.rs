@@ -0,0 +1,150 @@
+//! The HTTP layer used by [`Celcat`](super::Celcat).
+//!
+//! [`ReqwestTransport`] is used by default. [`reqwest::Client`] also implements
+//! [`Transport`], and can be given to
+//! [`Celcat::with_transport`](super::Celcat::with_transport) to use a proxy,
+//! custom TLS roots or timeouts, but then the session can't be saved.
+//! Other implementations can mock or cache Celcat.
+
+use std::{fmt::Debug, sync::Arc};
+
+use futures::future::BoxFuture;
+use log::warn;
+use reqwest::{
+    cookie::{CookieStore, Jar},
+    Url,
+};
+use serde::{Deserialize, Serialize};
+
+use super::FetchError;
+
+/// A response, once all the redirections have been followed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HttpResponse {
+    pub status: u16,
+    /// The final URL, after the redirections.
+    pub url: String,
+    pub content_type: Option<String>,
+    pub body: String,
+}
+
+impl HttpResponse {
+    pub fn is_html(&self) -> bool {
+        self.content_type
+            .as_deref()
+            .is_some_and(|ct| ct.starts_with("text/html"))
+    }
+}
+
+/// Sends the HTTP requests of a [`Celcat`](super::Celcat) client.
+///
+/// A transport must follow redirections and keep the cookies it receives,
+/// as the Celcat session is stored in a cookie.
+pub trait Transport: Debug + Send + Sync {
+    fn get<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<HttpResponse, FetchError>>;
+
+    /// Sends `form` as `application/x-www-form-urlencoded`.
+    fn post_form<'a>(
+        &'a self,
+        url: &'a str,
+        form: &'a [(String, String)],
+    ) -> BoxFuture<'a, Result<HttpResponse, FetchError>>;
+
+    /// The cookies which would be sent to `url`, as `name=value` strings.
+    ///
+    /// Used to save the session, see [`Celcat::session`](super::Celcat::session).
+    /// By default, no cookie is returned.
+    fn cookies(&self, _url: &str) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Stores `cookies`, as returned by [`Transport::cookies`], for `url`.
+    ///
+    /// By default, they are ignored.
+    fn set_cookies(&self, _url: &str, _cookies: &[String]) {}
+}
+
+/// A [`reqwest::Client`] whose cookies are accessible.
+///
+/// This is the transport used by default.
+#[derive(Debug, Clone)]
+pub struct ReqwestTransport {
+    client: reqwest::Client,
+    jar: Arc<Jar>,
+}
+
+impl ReqwestTransport {
+    /// `client` must have been built with `jar` as its cookie provider.
+    pub fn new(client: reqwest::Client, jar: Arc<Jar>) -> Self {
+        Self { client, jar }
+    }
+}
+
+impl Transport for ReqwestTransport {
+    fn get<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<HttpResponse, FetchError>> {
+        Transport::get(&self.client, url)
+    }
+
+    fn post_form<'a>(
+        &'a self,
+        url: &'a str,
+        form: &'a [(String, String)],
+    ) -> BoxFuture<'a, Result<HttpResponse, FetchError>> {
+        self.client.post_form(url, form)
+    }
+
+    fn cookies(&self, url: &str) -> Vec<String> {
+        let url = match Url::parse(url) {
+            Ok(url) => url,
+            Err(e) => {
+                warn!("invalid URL {}: {}", url, e);
+                return Vec::new();
+            }
+        };
+        self.jar
+            .cookies(&url)
+            .and_then(|header| header.to_str().ok().map(str::to_owned))
+            .map(|header| header.split("; ").map(str::to_owned).collect())
+            .unwrap_or_default()
+    }
+
+    fn set_cookies(&self, url: &str, cookies: &[String]) {
+        match Url::parse(url) {
+            Ok(url) => {
+                for cookie in cookies {
+                    self.jar
+                        .add_cookie_str(&format!("{}; Path=/", cookie), &url);
+                }
+            }
+            Err(e) => warn!("invalid URL {}: {}", url, e),
+        }
+    }
+}
+
+/// The client must have been built with a cookie store.
+impl Transport for reqwest::Client {
+    fn get<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<HttpResponse, FetchError>> {
+        Box::pin(async move { read(self.get(url).send().await?).await })
+    }
+
+    fn post_form<'a>(
+        &'a self,
+        url: &'a str,
+        form: &'a [(String, String)],
+    ) -> BoxFuture<'a, Result<HttpResponse, FetchError>> {
+        Box::pin(async move { read(self.post(url).form(form).send().await?).await })
+    }
+}
+
+async fn read(res: reqwest::Response) -> Result<HttpResponse, FetchError> {
+    Ok(HttpResponse {
+        status: res.status().as_u16(),
+        url: res.url().to_string(),
+        content_type: res
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|ct| ct.to_str().ok())
+            .map(str::to_owned),
+        body: res.text().await?,
+    })
+}
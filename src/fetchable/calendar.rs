@@ -1,12 +1,22 @@
-use std::marker::PhantomData;
+mod description;
 
-use chrono::NaiveDateTime;
-use serde::{Deserialize, Deserializer, Serialize};
+use std::{collections::HashSet, marker::PhantomData};
+
+#[cfg(feature = "tz")]
+use chrono::{offset::LocalResult, DateTime, Duration, TimeZone};
+use chrono::{NaiveDate, NaiveDateTime};
+#[cfg(feature = "tz")]
+use chrono_tz::Tz;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Map, Value};
 
 use super::Fetchable;
 use crate::entities::{CourseId, ModuleId, ResourceType};
 
-#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub(crate) use description::lines as description_lines;
+pub use description::Description;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 #[non_exhaustive]
 pub struct Course {
@@ -22,14 +32,132 @@ pub struct Course {
     pub event_category: Option<String>,
     pub sites: Option<Vec<String>>,
     pub modules: Option<Vec<ModuleId>>,
-    pub register_status: i64, // TODO: values?
+    pub register_status: RegisterStatus,
     pub student_mark: f64,
+    /// Fields configured by each university.
+    pub custom1: Option<String>,
+    pub custom2: Option<String>,
+    pub custom3: Option<String>,
+    /// The fields unknown to this crate, kept so that they are serialized back.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl Course {
+    /// Extracts the information contained in the description.
+    pub fn parse_description(&self) -> Description {
+        Description::parse(&self.description)
+    }
+}
+
+/// The timezone of the times sent and received by Celcat.
+#[cfg(feature = "tz")]
+pub const TIMEZONE: Tz = chrono_tz::Europe::Paris;
+
+/// Interprets `naive` as a time in [`TIMEZONE`].
+///
+/// An ambiguous time, when the clocks go back, is taken as the earliest one.
+/// A time which doesn't exist, when the clocks go forward, is taken as
+/// a time in winter.
+#[cfg(feature = "tz")]
+pub fn localize(naive: NaiveDateTime) -> DateTime<Tz> {
+    match TIMEZONE.from_local_datetime(&naive) {
+        LocalResult::Single(t) | LocalResult::Ambiguous(t, _) => t,
+        LocalResult::None => TIMEZONE.from_utc_datetime(&(naive - Duration::hours(1))),
+    }
+}
+
+#[cfg(feature = "tz")]
+impl Course {
+    pub fn start_tz(&self) -> DateTime<Tz> {
+        localize(self.start)
+    }
+
+    pub fn end_tz(&self) -> Option<DateTime<Tz>> {
+        self.end.map(localize)
+    }
+}
+
+/// An enum sent as an integer, keeping the unknown values.
+macro_rules! int_enum {
+    (
+        $(#[$meta:meta])*
+        pub enum $name:ident {
+            $($(#[$vmeta:meta])* $variant:ident = $value:literal,)*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+        #[serde(from = "i64", into = "i64")]
+        #[non_exhaustive]
+        pub enum $name {
+            $($(#[$vmeta])* $variant,)*
+            /// A value whose meaning is unknown.
+            Other(i64),
+        }
+
+        impl From<i64> for $name {
+            fn from(value: i64) -> Self {
+                match value {
+                    $($value => Self::$variant,)*
+                    value => Self::Other(value),
+                }
+            }
+        }
+
+        impl From<$name> for i64 {
+            fn from(value: $name) -> Self {
+                match value {
+                    $($name::$variant => $value,)*
+                    $name::Other(value) => value,
+                }
+            }
+        }
+    };
+}
+
+int_enum! {
+    /// Whether the attendance of a course has been taken.
+    ///
+    /// The web app shows a tick on the courses whose register has been taken.
+    pub enum RegisterStatus {
+        /// The attendance hasn't been taken, or isn't taken for this course.
+        NotRegistered = 0,
+        /// The attendance has been taken for some of the students.
+        PartiallyRegistered = 1,
+        /// The attendance has been taken.
+        Registered = 2,
+    }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+int_enum! {
+    /// What the colour of the courses depends on, as chosen in the
+    /// "Colour by" menu of the web app.
+    ///
+    /// It only affects [`Course::background_color`] and [`Course::text_color`].
+    #[derive(Default)]
+    pub enum ColourScheme {
+        /// The colour of the department.
+        Department = 1,
+        /// The colour of the module.
+        Module = 2,
+        /// The colour of the category (CM, TD, exam...), which is the default.
+        #[default]
+        Category = 3,
+        /// The colour of the room.
+        Room = 4,
+        /// The colour of the staff.
+        Staff = 5,
+        /// The colour of the group.
+        Group = 6,
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 #[non_exhaustive]
 pub enum CalView {
+    #[default]
     Month,
     AgendaWeek,
     AgendaDay,
@@ -43,10 +171,71 @@ pub struct CalendarDataRequest<T: ResourceType> {
     pub end: NaiveDateTime,
     pub res_type: T,
     pub cal_view: CalView,
-    pub federation_ids: T::Id, // TODO: array
-    pub colour_scheme: i64,    // TODO: values?
+    /// Several resources can be requested at once, their calendars are merged.
+    pub federation_ids: Vec<T::Id>,
+    pub colour_scheme: ColourScheme,
 }
 
+impl<T> CalendarDataRequest<T>
+where
+    T: ResourceType,
+{
+    /// A request for the calendar of `federation_ids` between `start` and `end`,
+    /// in month view with the default colour scheme.
+    pub fn new(
+        res_type: T,
+        federation_ids: Vec<T::Id>,
+        start: NaiveDateTime,
+        end: NaiveDateTime,
+    ) -> Self {
+        Self {
+            start,
+            end,
+            res_type,
+            cal_view: CalView::default(),
+            federation_ids,
+            colour_scheme: ColourScheme::default(),
+        }
+    }
+
+    /// A request for the days from `first` to `last`, both included.
+    pub fn from_dates(
+        res_type: T,
+        federation_ids: Vec<T::Id>,
+        first: NaiveDate,
+        last: NaiveDate,
+    ) -> Self {
+        Self::new(
+            res_type,
+            federation_ids,
+            first.and_hms(0, 0, 0),
+            last.succ().and_hms(0, 0, 0),
+        )
+    }
+
+    /// A request between two instants, in any timezone.
+    #[cfg(feature = "tz")]
+    pub fn from_datetimes<Z>(
+        res_type: T,
+        federation_ids: Vec<T::Id>,
+        start: DateTime<Z>,
+        end: DateTime<Z>,
+    ) -> Self
+    where
+        Z: TimeZone,
+    {
+        Self::new(
+            res_type,
+            federation_ids,
+            start.with_timezone(&TIMEZONE).naive_local(),
+            end.with_timezone(&TIMEZONE).naive_local(),
+        )
+    }
+}
+
+/// The courses of the requested resources.
+///
+/// A course shared by several requested resources is only present once.
 #[derive(Debug, Clone)]
 pub struct CalendarData<T: ResourceType> {
     pub courses: Vec<Course>,
@@ -70,19 +259,58 @@ where
     where
         D: Deserializer<'de>,
     {
-        Vec::<Course>::deserialize(deserializer).map(|cs| CalendarData {
-            courses: cs,
-            request: PhantomData,
+        let mut seen = HashSet::new();
+        Vec::<Course>::deserialize(deserializer).map(|mut cs| {
+            cs.retain(|c| seen.insert(c.id.clone()));
+            CalendarData {
+                courses: cs,
+                request: PhantomData,
+            }
         })
     }
 }
 
+/// Serialized as the sequence of its courses, like Celcat does.
+impl<T> Serialize for CalendarData<T>
+where
+    T: ResourceType,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.courses.serialize(serializer)
+    }
+}
+
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
-    use crate::entities::Student;
-    use chrono::NaiveDate;
-    use serde_json::{from_value, json};
+    use crate::entities::{DynEntityId, DynEntityType, Group, GroupId, Student};
+    use serde_json::{from_value, json, to_value};
+
+    /// A course as sent by Celcat, with only the required fields set.
+    pub(crate) fn course_json(id: &str) -> Value {
+        json!({
+            "id": id,
+            "start": "2021-09-22T14:30:00",
+            "end": null,
+            "allDay": false,
+            "description": "",
+            "backgroundColor": "#FF0000",
+            "textColor": "#ffffff",
+            "department": null,
+            "faculty": null,
+            "eventCategory": null,
+            "sites": null,
+            "modules": null,
+            "registerStatus": 0,
+            "studentMark": 0.0,
+            "custom1": null,
+            "custom2": null,
+            "custom3": null
+        })
+    }
 
     #[test]
     fn deserialize_course() {
@@ -124,8 +352,12 @@ mod tests {
                 event_category: Some("CM".to_owned()),
                 sites: Some(vec!["CHENES".to_owned()]),
                 modules: Some(vec![ModuleId("1BAIJU1M".to_owned())]),
-                register_status: 2,
+                register_status: RegisterStatus::Registered,
                 student_mark: 0.0,
+                custom1: None,
+                custom2: None,
+                custom3: None,
+                extra: Map::new(),
             }
         );
     }
@@ -134,4 +366,149 @@ mod tests {
     fn deserialize_calendar_data() {
         from_value::<CalendarData<Student>>(json!([])).unwrap();
     }
+
+    #[test]
+    fn round_trip_course() {
+        let json = json!({
+            "id": "1",
+            "start": "2021-09-22T14:30:00",
+            "end": "2021-09-22T17:45:00",
+            "allDay": false,
+            "description": "Some description",
+            "backgroundColor": "#FF0000",
+            "textColor": "#ffffff",
+            "department": null,
+            "faculty": null,
+            "eventCategory": "CM",
+            "sites": null,
+            "modules": null,
+            "registerStatus": 7,
+            "studentMark": 0.5,
+            "custom1": "A",
+            "custom2": null,
+            "custom3": null,
+            "newField": [1, 2]
+        });
+        let course: Course = from_value(json.clone()).unwrap();
+        assert_eq!(course.custom1.as_deref(), Some("A"));
+        assert_eq!(course.register_status, RegisterStatus::Other(7));
+        assert_eq!(course.extra["newField"], json!([1, 2]));
+        assert_eq!(to_value(&course).unwrap(), json);
+    }
+
+    #[test]
+    fn round_trip_calendar_data() {
+        let mut course = course_json("1");
+        course["modules"] = json!(["1BAIJU1M"]);
+        let json = json!([course]);
+        let data: CalendarData<Group> = from_value(json.clone()).unwrap();
+        assert_eq!(to_value(&data).unwrap(), json);
+    }
+
+    #[test]
+    fn deduplicate_courses() {
+        let data = from_value::<CalendarData<Group>>(json!([
+            course_json("1"),
+            course_json("2"),
+            course_json("1")
+        ]))
+        .unwrap();
+        assert_eq!(
+            data.courses.iter().map(|c| &c.id.0[..]).collect::<Vec<_>>(),
+            ["1", "2"]
+        );
+    }
+
+    #[test]
+    fn int_enums() {
+        assert_eq!(
+            from_value::<RegisterStatus>(json!(2)).unwrap(),
+            RegisterStatus::Registered
+        );
+        assert_eq!(
+            from_value::<RegisterStatus>(json!(42)).unwrap(),
+            RegisterStatus::Other(42)
+        );
+        assert_eq!(to_value(RegisterStatus::Other(42)).unwrap(), json!(42));
+        assert_eq!(to_value(ColourScheme::Category).unwrap(), json!(3));
+        assert_eq!(
+            from_value::<ColourScheme>(json!(-1)).unwrap(),
+            ColourScheme::Other(-1)
+        );
+    }
+
+    #[test]
+    fn request_from_dates() {
+        let req = CalendarDataRequest::from_dates(
+            Group,
+            vec![GroupId("A".to_owned())],
+            NaiveDate::from_ymd(2021, 9, 20),
+            NaiveDate::from_ymd(2021, 9, 26),
+        );
+        assert_eq!(req.start, NaiveDate::from_ymd(2021, 9, 20).and_hms(0, 0, 0));
+        assert_eq!(req.end, NaiveDate::from_ymd(2021, 9, 27).and_hms(0, 0, 0));
+    }
+
+    #[cfg(feature = "tz")]
+    #[test]
+    fn timezone() {
+        use chrono::{Timelike, Utc};
+
+        let winter = localize(NaiveDate::from_ymd(2021, 12, 1).and_hms(8, 0, 0));
+        assert_eq!(winter.with_timezone(&Utc).hour(), 7);
+        let summer = localize(NaiveDate::from_ymd(2021, 6, 1).and_hms(8, 0, 0));
+        assert_eq!(summer.with_timezone(&Utc).hour(), 6);
+        // The clocks go back at 3:00, and forward at 2:00
+        let fall = localize(NaiveDate::from_ymd(2021, 10, 31).and_hms(2, 30, 0));
+        assert_eq!(fall.with_timezone(&Utc).hour(), 0);
+        let spring = localize(NaiveDate::from_ymd(2021, 3, 28).and_hms(2, 30, 0));
+        assert_eq!(spring.with_timezone(&Utc).hour(), 1);
+
+        let req = CalendarDataRequest::from_datetimes(
+            Group,
+            vec![GroupId("A".to_owned())],
+            Utc.ymd(2021, 9, 20).and_hms(0, 0, 0),
+            Utc.ymd(2021, 9, 27).and_hms(0, 0, 0),
+        );
+        assert_eq!(req.start, NaiveDate::from_ymd(2021, 9, 20).and_hms(2, 0, 0));
+    }
+
+    #[test]
+    fn serialize_calendar_data_request() {
+        assert_eq!(
+            to_value(CalendarDataRequest {
+                start: NaiveDate::from_ymd(2021, 9, 20).and_hms(0, 0, 0),
+                end: NaiveDate::from_ymd(2021, 9, 27).and_hms(0, 0, 0),
+                res_type: Group,
+                cal_view: CalView::AgendaWeek,
+                federation_ids: vec![GroupId("A".to_owned()), GroupId("B".to_owned())],
+                colour_scheme: ColourScheme::Category,
+            })
+            .unwrap(),
+            json!({
+                "start": "2021-09-20T00:00:00",
+                "end": "2021-09-27T00:00:00",
+                "resType": 103,
+                "calView": "agendaWeek",
+                "federationIds": ["A", "B"],
+                "colourScheme": 3
+            })
+        );
+    }
+
+    #[test]
+    fn serialize_dyn_calendar_data_request() {
+        assert_eq!(
+            to_value(CalendarDataRequest {
+                start: NaiveDate::from_ymd(2021, 9, 20).and_hms(0, 0, 0),
+                end: NaiveDate::from_ymd(2021, 9, 27).and_hms(0, 0, 0),
+                res_type: "room".parse::<DynEntityType>().unwrap(),
+                cal_view: CalView::Month,
+                federation_ids: vec![DynEntityId(Some("1172982".to_owned()))],
+                colour_scheme: ColourScheme::Category,
+            })
+            .unwrap()["resType"],
+            json!(102)
+        );
+    }
 }
@@ -0,0 +1,235 @@
+use crate::entities::ModuleId;
+
+/// The information contained in [`Course::description`](super::Course::description).
+///
+/// Celcat puts one piece of information per line, separated by `<br />`,
+/// in this order: the category, the modules (`NAME [CODE]`),
+/// the rooms (`SITE; ROOM`), the staff, the groups, and free text.
+///
+/// Nothing marks which kind each line is, so the staff and groups are guessed:
+/// a name has lowercase letters and no digit, like `DUPONT Jean`,
+/// whereas a group has no lowercase letter, like `L1 DROIT GR A`.
+/// Staff written in capitals only, like `DUPONT JEAN`, can't be told apart
+/// from a group, so they end up in [`groups`](Self::groups).
+/// Everything after the groups is kept in [`notes`](Self::notes).
+/// [`Event`](crate::fetchable::event::Event) is more reliable, but needs
+/// one request per course.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Description {
+    pub category: Option<String>,
+    pub modules: Vec<(ModuleId, String)>,
+    pub rooms: Vec<String>,
+    pub staff: Vec<String>,
+    pub groups: Vec<String>,
+    pub notes: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Section {
+    Category,
+    Modules,
+    Rooms,
+    Staff,
+    Groups,
+    Notes,
+}
+
+impl Description {
+    pub fn parse(description: &str) -> Self {
+        let mut desc = Self::default();
+        let mut section = Section::Category;
+        for line in lines(description) {
+            let module = parse_module(&line);
+            let kind = if module.is_some() {
+                Section::Modules
+            } else if line.contains("; ") {
+                Section::Rooms
+            } else if section == Section::Category {
+                Section::Category
+            } else if is_name(&line) {
+                Section::Staff
+            } else if !line.chars().any(char::is_lowercase) {
+                Section::Groups
+            } else {
+                Section::Notes
+            };
+            // Each section comes after the previous ones
+            section = if kind < section { Section::Notes } else { kind };
+
+            match section {
+                Section::Category => desc.category = Some(line),
+                Section::Modules => desc.modules.extend(module),
+                Section::Rooms => desc.rooms.push(line),
+                Section::Staff => desc.staff.push(line),
+                Section::Groups => desc.groups.push(line),
+                Section::Notes => desc.notes.push(line),
+            }
+            if section == Section::Category {
+                section = Section::Modules;
+            }
+        }
+        desc
+    }
+}
+
+/// Splits the HTML description into its decoded, non-empty lines.
+pub(crate) fn lines(description: &str) -> impl Iterator<Item = String> + '_ {
+    description
+        .split("<br />")
+        .flat_map(|l| l.split("<br/>"))
+        .flat_map(|l| l.split("<br>"))
+        .flat_map(str::lines)
+        .map(|l| decode_entities(&strip_tags(l)).trim().to_owned())
+        .filter(|l| !l.is_empty())
+}
+
+/// `NAME [CODE]`.
+fn parse_module(line: &str) -> Option<(ModuleId, String)> {
+    let (name, code) = line.strip_suffix(']')?.rsplit_once('[')?;
+    let name = name.trim();
+    if name.is_empty() || code.is_empty() || code.contains(' ') {
+        return None;
+    }
+    Some((ModuleId(code.to_owned()), name.to_owned()))
+}
+
+/// Whether `line` looks like the name of a person, like `DUPONT Jean`.
+fn is_name(line: &str) -> bool {
+    let words = line.split_whitespace().count();
+    (2..=5).contains(&words)
+        && line.chars().any(char::is_lowercase)
+        && line
+            .chars()
+            .all(|c| c.is_alphabetic() || matches!(c, ' ' | '-' | '\'' | '.'))
+}
+
+fn strip_tags(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut in_tag = false;
+    for c in s.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            c if !in_tag => out.push(c),
+            _ => {}
+        }
+    }
+    out
+}
+
+/// Decodes the numeric HTML entities, and the named ones found in French text.
+fn decode_entities(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let decoded = rest
+            .find(';')
+            .filter(|&end| end <= 10)
+            .and_then(|end| decode_entity(&rest[1..end]).map(|c| (c, end)));
+        match decoded {
+            Some((c, end)) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn decode_entity(entity: &str) -> Option<char> {
+    if let Some(num) = entity.strip_prefix('#') {
+        let code = match num.strip_prefix('x').or_else(|| num.strip_prefix('X')) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => num.parse().ok()?,
+        };
+        return char::from_u32(code);
+    }
+    Some(match entity {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => '\u{a0}',
+        "agrave" => 'à',
+        "acirc" => 'â',
+        "ccedil" => 'ç',
+        "eacute" => 'é',
+        "egrave" => 'è',
+        "ecirc" => 'ê',
+        "euml" => 'ë',
+        "icirc" => 'î',
+        "iuml" => 'ï',
+        "ocirc" => 'ô',
+        "ugrave" => 'ù',
+        "ucirc" => 'û',
+        "uuml" => 'ü',
+        "Agrave" => 'À',
+        "Ccedil" => 'Ç',
+        "Eacute" => 'É',
+        "Egrave" => 'È',
+        "Ecirc" => 'Ê',
+        "oelig" => 'œ',
+        "rsquo" => '’',
+        "laquo" => '«',
+        "raquo" => '»',
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_description() {
+        let desc = Description::parse(
+            "CM\r\n\r\n<br />DROIT CIVIL [1BAIJU1M]\r\n\r\n<br />CHENES; AMPHI, A\r\n\r\n\
+             <br />DUPONT Jean\r\n\r\n<br />MARTIN-L&Eacute;GER Anne\r\n\r\n\
+             <br />L1 DROIT GR A\r\n\r\n<br />L1 DROIT GR B\r\n\r\n\
+             <br />Apporter le code civil &amp; un stylo",
+        );
+        assert_eq!(desc.category.as_deref(), Some("CM"));
+        assert_eq!(
+            desc.modules,
+            [(ModuleId("1BAIJU1M".to_owned()), "DROIT CIVIL".to_owned())]
+        );
+        assert_eq!(desc.rooms, ["CHENES; AMPHI, A"]);
+        assert_eq!(desc.staff, ["DUPONT Jean", "MARTIN-LÉGER Anne"]);
+        assert_eq!(desc.groups, ["L1 DROIT GR A", "L1 DROIT GR B"]);
+        assert_eq!(desc.notes, ["Apporter le code civil & un stylo"]);
+    }
+
+    #[test]
+    fn partial_description() {
+        let desc = Description::parse("TD<br />L2 INFO GR1<br />Salle informatique");
+        assert_eq!(desc.category.as_deref(), Some("TD"));
+        assert!(desc.modules.is_empty());
+        assert!(desc.staff.is_empty());
+        assert_eq!(desc.groups, ["L2 INFO GR1"]);
+        assert_eq!(desc.notes, ["Salle informatique"]);
+
+        assert_eq!(Description::parse(""), Description::default());
+
+        // Indistinguishable from a group
+        let desc = Description::parse("TD<br />DUPONT JEAN<br />L2 INFO GR1");
+        assert!(desc.staff.is_empty());
+        assert_eq!(desc.groups, ["DUPONT JEAN", "L2 INFO GR1"]);
+    }
+
+    #[test]
+    fn decode() {
+        assert_eq!(
+            decode_entities("&lt;b&gt; d&#233;j&#xE0; &amp;amp; &unknown; &"),
+            "<b> déjà &amp; &unknown; &"
+        );
+    }
+}
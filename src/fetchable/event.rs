@@ -1,18 +1,25 @@
+mod time;
+
 use std::fmt;
 
 use serde::{
     de::{self, SeqAccess, Visitor},
-    Deserialize, Deserializer, Serialize,
+    ser::SerializeSeq,
+    Deserialize, Deserializer, Serialize, Serializer,
 };
 use serde_json::Value;
 
 use super::Fetchable;
-use crate::entities::{CourseId, EntityType, Module, Room, Staff, Unknown, UnknownId};
+use crate::entities::{
+    CourseId, EntityType, Module, ModuleId, Room, RoomId, Staff, StaffId, Unknown, UnknownId,
+};
+
+pub use time::{EventTime, ParseEventTimeError};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Elements(pub Vec<Element>);
 
-#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Event {
     pub federation_id: UnknownId,
@@ -20,14 +27,14 @@ pub struct Event {
     pub elements: Elements,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 #[non_exhaustive]
 pub struct RawElement<T: EntityType> {
     pub content: Option<String>,
-    #[serde(bound(deserialize = "T: EntityType"))]
+    #[serde(bound(serialize = "T: EntityType", deserialize = "T: EntityType"))]
     pub federation_id: T::Id,
-    #[serde(bound(deserialize = "T: EntityType"))]
+    #[serde(bound(serialize = "T: EntityType", deserialize = "T: EntityType"))]
     pub entity_type: T,
     pub assignment_context: Option<String>,
     pub contains_hyperlinks: bool,
@@ -47,6 +54,211 @@ pub enum Element {
     Name(RawElement<Unknown>),
 }
 
+impl Event {
+    /// Parses the content of the first [`Element::Time`].
+    pub fn time(&self) -> Option<Result<EventTime, ParseEventTimeError>> {
+        self.elements.0.iter().find_map(Element::time)
+    }
+
+    /// The content of the first [`Element::Category`].
+    pub fn category(&self) -> Option<&str> {
+        self.find_content(|e| match e {
+            Element::Category(e) => Some(e),
+            _ => None,
+        })
+    }
+
+    /// The content of the first [`Element::Grade`].
+    pub fn notes(&self) -> Option<&str> {
+        self.find_content(|e| match e {
+            Element::Grade(e) => Some(e),
+            _ => None,
+        })
+    }
+
+    /// The content of the first [`Element::Name`].
+    pub fn name(&self) -> Option<&str> {
+        self.find_content(|e| match e {
+            Element::Name(e) => Some(e),
+            _ => None,
+        })
+    }
+
+    /// The first [`Element::Module`].
+    pub fn module(&self) -> Option<(ModuleId, String)> {
+        self.elements.0.iter().find_map(|e| match e {
+            Element::Module(e) => Some(id_and_content(e)),
+            _ => None,
+        })
+    }
+
+    /// Every [`Element::Room`], in order.
+    pub fn rooms(&self) -> Vec<(RoomId, String)> {
+        self.room_groups().concat()
+    }
+
+    /// Every [`Element::Teacher`], in order.
+    pub fn teachers(&self) -> Vec<(StaffId, String)> {
+        self.teacher_groups().concat()
+    }
+
+    /// Every [`Element::Room`], grouped by assignment.
+    ///
+    /// See [`Event::teacher_groups`].
+    pub fn room_groups(&self) -> Vec<Vec<(RoomId, String)>> {
+        self.groups(|e| match e {
+            Element::Room(e) => Some(e),
+            _ => None,
+        })
+    }
+
+    /// Every [`Element::Teacher`], grouped by assignment.
+    ///
+    /// An assignment is a list of elements whose `assignment_context` goes from
+    /// `a-start` to `a-end-N`, or a single element marked `a-start-end`.
+    pub fn teacher_groups(&self) -> Vec<Vec<(StaffId, String)>> {
+        self.groups(|e| match e {
+            Element::Teacher(e) => Some(e),
+            _ => None,
+        })
+    }
+
+    fn find_content<F>(&self, f: F) -> Option<&str>
+    where
+        F: Fn(&Element) -> Option<&RawElement<Unknown>>,
+    {
+        self.elements
+            .0
+            .iter()
+            .find_map(f)
+            .and_then(|e| e.content.as_deref())
+    }
+
+    fn groups<T, F>(&self, f: F) -> Vec<Vec<(T::Id, String)>>
+    where
+        T: EntityType,
+        F: Fn(&Element) -> Option<&RawElement<T>>,
+    {
+        let mut groups: Vec<Vec<_>> = Vec::new();
+        let mut in_group = false;
+        for element in &self.elements.0 {
+            match f(element) {
+                Some(e) => {
+                    let starts = e
+                        .assignment_context
+                        .as_deref()
+                        .is_some_and(|c| c.contains("start"));
+                    match groups.last_mut() {
+                        Some(group) if in_group && !starts => group.push(id_and_content(e)),
+                        _ => groups.push(vec![id_and_content(e)]),
+                    }
+                    in_group = true;
+                }
+                None => in_group = false,
+            }
+        }
+        groups
+    }
+}
+
+fn id_and_content<T: EntityType>(e: &RawElement<T>) -> (T::Id, String) {
+    (
+        e.federation_id.clone(),
+        e.content.clone().unwrap_or_default(),
+    )
+}
+
+impl Element {
+    /// Parses the content of a [`Element::Time`].
+    ///
+    /// Returns `None` for other elements, or if there is no content.
+    pub fn time(&self) -> Option<Result<EventTime, ParseEventTimeError>> {
+        match self {
+            Self::Time(e) => e.content.as_deref().map(str::parse),
+            _ => None,
+        }
+    }
+
+    /// The label Celcat gives to the first element of a run.
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Time(_) => "Date",
+            Self::Category(_) => "Catégorie",
+            Self::Module(_) => "Matière",
+            Self::Room(_) => "Salle",
+            Self::Teacher(_) => "Enseignant",
+            Self::Grade(_) => "Note",
+            Self::Name(_) => "Name",
+        }
+    }
+
+    fn serialize_with_label<S>(&self, label: Option<&str>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        #[derive(Serialize)]
+        struct Labelled<'a, T: EntityType> {
+            label: Option<&'a str>,
+            #[serde(flatten)]
+            element: &'a RawElement<T>,
+        }
+
+        match self {
+            Self::Time(element)
+            | Self::Category(element)
+            | Self::Grade(element)
+            | Self::Name(element) => Labelled { label, element }.serialize(serializer),
+            Self::Module(element) => Labelled { label, element }.serialize(serializer),
+            Self::Room(element) => Labelled { label, element }.serialize(serializer),
+            Self::Teacher(element) => Labelled { label, element }.serialize(serializer),
+        }
+    }
+}
+
+/// Serialized as a labelled side bar element.
+impl Serialize for Element {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.serialize_with_label(Some(self.label()), serializer)
+    }
+}
+
+/// Serialized like Celcat does: only the first element of a run of the same
+/// kind is labelled, the following ones have a `null` label.
+impl Serialize for Elements {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        struct WithLabel<'a>(&'a Element, Option<&'a str>);
+
+        impl Serialize for WithLabel<'_> {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: Serializer,
+            {
+                self.0.serialize_with_label(self.1, serializer)
+            }
+        }
+
+        let mut seq = serializer.serialize_seq(Some(self.0.len()))?;
+        let mut last_label = None;
+        for element in &self.0 {
+            let label = element.label();
+            let label = if last_label == Some(label) {
+                None
+            } else {
+                last_label = Some(label);
+                Some(label)
+            };
+            seq.serialize_element(&WithLabel(element, label))?;
+        }
+        seq.end()
+    }
+}
+
 impl<'de> Deserialize<'de> for Elements {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -153,9 +365,29 @@ impl Fetchable for Event {
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
-    use serde_json::{from_value, json};
+    use serde_json::{from_value, json, to_value, Value};
+
+    /// An element of a side bar event as sent by Celcat.
+    pub(crate) fn element_json(
+        label: Option<&str>,
+        content: &str,
+        id: Option<&str>,
+        entity_type: u8,
+        context: Option<&str>,
+    ) -> Value {
+        json!({
+            "label": label,
+            "content": content,
+            "federationId": id,
+            "entityType": entity_type,
+            "assignmentContext": context,
+            "containsHyperlinks": false,
+            "isNotes": false,
+            "isStudentSpecific": false
+        })
+    }
 
     #[test]
     fn deserialize_element() {
@@ -280,6 +512,116 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn round_trip_event() {
+        let element =
+            |label, id, entity_type| element_json(label, "SOMETHING", Some(id), entity_type, None);
+        let json = json!({
+            "federationId": null,
+            "entityType": 0,
+            "elements": [
+                element(Some("Matière"), "DPGANG3D", 100),
+                element(Some("Salle"), "1172982", 102),
+                element(None, "1172981", 102),
+                element(Some("Enseignant"), "012345", 101),
+            ]
+        });
+        let event: Event = from_value(json.clone()).unwrap();
+        assert_eq!(to_value(&event).unwrap(), json);
+        assert_eq!(
+            from_value::<Event>(to_value(&event).unwrap()).unwrap(),
+            event
+        );
+        assert_eq!(
+            to_value(&event.elements.0[2]).unwrap(),
+            element(Some("Salle"), "1172981", 102)
+        );
+    }
+
+    #[test]
+    fn element_time() {
+        let time = from_value::<Elements>(json!([element_json(
+            Some("Date"),
+            "11/9/2021 2:01 PM-5:16 PM",
+            None,
+            0,
+            None
+        )]))
+        .unwrap()
+        .0[0]
+            .time()
+            .unwrap()
+            .unwrap();
+        assert_eq!(time.start, chrono::NaiveTime::from_hms(14, 1, 0));
+    }
+
+    #[test]
+    fn event_details() {
+        let elements = vec![
+            element_json(Some("Date"), "11/9/2021 2:01 PM-5:16 PM", None, 0, None),
+            element_json(Some("Catégorie"), "TD", None, 0, None),
+            element_json(
+                Some("Matière"),
+                "Anglais [DPGANG3D]",
+                Some("DPGANG3D"),
+                100,
+                Some("a-start-end"),
+            ),
+            element_json(Some("Salles"), "ROOM 1", Some("1"), 102, Some("a-start")),
+            element_json(None, "ROOM 2", Some("2"), 102, Some("a-end-0")),
+            element_json(None, "ROOM 3", Some("3"), 102, Some("a-start-end")),
+            element_json(
+                Some("Enseignant"),
+                "SOME BODY",
+                Some("012345"),
+                101,
+                Some("a-start-end"),
+            ),
+            element_json(Some("Notes"), "Bring a laptop", None, 0, None),
+        ];
+        let event = from_value::<Event>(json!({
+            "federationId": null,
+            "entityType": 0,
+            "elements": elements
+        }))
+        .unwrap();
+
+        assert_eq!(
+            event.time().unwrap().unwrap().end,
+            chrono::NaiveTime::from_hms(17, 16, 0)
+        );
+        assert_eq!(event.category(), Some("TD"));
+        assert_eq!(event.notes(), Some("Bring a laptop"));
+        assert_eq!(event.name(), None);
+        assert_eq!(
+            event.module(),
+            Some((
+                ModuleId("DPGANG3D".to_owned()),
+                "Anglais [DPGANG3D]".to_owned()
+            ))
+        );
+        let room = |id: &str, name: &str| (RoomId(id.to_owned()), name.to_owned());
+        assert_eq!(
+            event.room_groups(),
+            [
+                vec![room("1", "ROOM 1"), room("2", "ROOM 2")],
+                vec![room("3", "ROOM 3")]
+            ]
+        );
+        assert_eq!(
+            event.rooms(),
+            [
+                room("1", "ROOM 1"),
+                room("2", "ROOM 2"),
+                room("3", "ROOM 3")
+            ]
+        );
+        assert_eq!(
+            event.teachers(),
+            [(StaffId("012345".to_owned()), "SOME BODY".to_owned())]
+        );
+    }
+
     #[test]
     fn deserialize_event() {
         from_value::<Event>(json!({
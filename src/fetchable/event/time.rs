@@ -0,0 +1,139 @@
+use std::{error::Error, fmt, str::FromStr};
+
+use chrono::{NaiveDate, NaiveTime};
+
+/// The content of the side bar "Date" element.
+///
+/// Depending on the culture of the session, Celcat either sends US dates and
+/// 12-hour times (`11/9/2021 2:01 PM-5:16 PM`), or French dates and 24-hour
+/// times (`09/11/2021 14:01-17:16`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EventTime {
+    pub date: NaiveDate,
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+/// Error returned when the content of a "Date" element can't be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseEventTimeError {
+    input: String,
+    reason: &'static str,
+}
+
+impl ParseEventTimeError {
+    fn new(input: &str, reason: &'static str) -> Self {
+        Self {
+            input: input.to_owned(),
+            reason,
+        }
+    }
+
+    /// The string which couldn't be parsed.
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+}
+
+impl fmt::Display for ParseEventTimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid event time `{}`: {}", self.input, self.reason)
+    }
+}
+
+impl Error for ParseEventTimeError {}
+
+impl FromStr for EventTime {
+    type Err = ParseEventTimeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = |reason| ParseEventTimeError::new(s, reason);
+
+        let (date, times) = s
+            .trim()
+            .split_once(' ')
+            .ok_or_else(|| err("missing time range"))?;
+        let (start, end) = times
+            .split_once('-')
+            .ok_or_else(|| err("missing end time"))?;
+        let (start, end) = (start.trim(), end.trim());
+
+        let twelve_hour = [start, end]
+            .iter()
+            .any(|t| t.ends_with("AM") || t.ends_with("PM"));
+        let (date_fmt, time_fmt) = if twelve_hour {
+            ("%m/%d/%Y", "%I:%M %p")
+        } else {
+            ("%d/%m/%Y", "%H:%M")
+        };
+
+        Ok(Self {
+            date: NaiveDate::parse_from_str(date, date_fmt).map_err(|_| err("invalid date"))?,
+            start: parse_time(start, time_fmt).ok_or_else(|| err("invalid start time"))?,
+            end: parse_time(end, time_fmt).ok_or_else(|| err("invalid end time"))?,
+        })
+    }
+}
+
+fn parse_time(s: &str, fmt: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(s, fmt)
+        .or_else(|_| NaiveTime::parse_from_str(s, "%Hh%M"))
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_us() {
+        assert_eq!(
+            "11/9/2021 2:01 PM-5:16 PM".parse(),
+            Ok(EventTime {
+                date: NaiveDate::from_ymd(2021, 11, 9),
+                start: NaiveTime::from_hms(14, 1, 0),
+                end: NaiveTime::from_hms(17, 16, 0),
+            })
+        );
+        assert_eq!(
+            "1/20/2022 11:30 AM - 12:45 PM".parse(),
+            Ok(EventTime {
+                date: NaiveDate::from_ymd(2022, 1, 20),
+                start: NaiveTime::from_hms(11, 30, 0),
+                end: NaiveTime::from_hms(12, 45, 0),
+            })
+        );
+    }
+
+    #[test]
+    fn parse_fr() {
+        assert_eq!(
+            "09/11/2021 14:01-17:16".parse(),
+            Ok(EventTime {
+                date: NaiveDate::from_ymd(2021, 11, 9),
+                start: NaiveTime::from_hms(14, 1, 0),
+                end: NaiveTime::from_hms(17, 16, 0),
+            })
+        );
+        assert_eq!(
+            "20/01/2022 8h30-10h00".parse(),
+            Ok(EventTime {
+                date: NaiveDate::from_ymd(2022, 1, 20),
+                start: NaiveTime::from_hms(8, 30, 0),
+                end: NaiveTime::from_hms(10, 0, 0),
+            })
+        );
+    }
+
+    #[test]
+    fn parse_invalid() {
+        let err = "11/9/2021".parse::<EventTime>().unwrap_err();
+        assert_eq!(err.input(), "11/9/2021");
+        assert_eq!(
+            err.to_string(),
+            "invalid event time `11/9/2021`: missing time range"
+        );
+        "31/31/2021 14:00-15:00".parse::<EventTime>().unwrap_err();
+        "11/9/2021 2:01 PM-25:16".parse::<EventTime>().unwrap_err();
+    }
+}
@@ -4,6 +4,10 @@ pub mod resources;
 
 use serde::{Deserialize, Serialize};
 
+/// A Celcat method, called with [`Celcat::fetch`](crate::Celcat::fetch).
+///
+/// Every method only reads data, so a request can safely be sent again
+/// after a failure.
 pub trait Fetchable: for<'de> Deserialize<'de> {
     type Request: Serialize;
 
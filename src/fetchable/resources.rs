@@ -3,19 +3,20 @@ use serde::{Deserialize, Serialize};
 use super::Fetchable;
 use crate::entities::ResourceType;
 
-#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Resource<R: ResourceType> {
+    #[serde(bound(serialize = "R: ResourceType", deserialize = "R: ResourceType"))]
     pub id: R::Id,
     pub text: String,
     pub dept: String,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ResourceList<R: ResourceType> {
     pub total: u64,
-    #[serde(bound(deserialize = "R: ResourceType"))]
+    #[serde(bound(serialize = "R: ResourceType", deserialize = "R: ResourceType"))]
     pub results: Vec<Resource<R>>,
 }
 
@@ -37,3 +38,20 @@ where
 
     const METHOD_NAME: &'static str = "ReadResourceListItems";
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::Room;
+    use serde_json::{from_value, json, to_value};
+
+    #[test]
+    fn round_trip_resource_list() {
+        let json = json!({
+            "total": 1,
+            "results": [{ "id": "1172982", "text": "A ROOM", "dept": "CY" }]
+        });
+        let list: ResourceList<Room> = from_value(json.clone()).unwrap();
+        assert_eq!(to_value(&list).unwrap(), json);
+    }
+}
@@ -0,0 +1,95 @@
+//! Encoding of requests as `application/x-www-form-urlencoded` pairs.
+//!
+//! `serde_urlencoded` can't serialize sequences, but Celcat (ASP.NET) expects
+//! a list to be sent as the same key repeated for every value.
+
+use serde::{ser::Error, Serialize};
+use serde_json::{Error as JsonError, Value};
+
+/// Flattens a request into the key/value pairs of a form.
+///
+/// `null` values are skipped, and arrays are sent as repeated keys.
+pub(crate) fn to_pairs<T>(req: &T) -> Result<Vec<(String, String)>, JsonError>
+where
+    T: Serialize,
+{
+    let fields = match serde_json::to_value(req)? {
+        Value::Object(fields) => fields,
+        _ => return Err(JsonError::custom("a request must be a struct")),
+    };
+
+    let mut pairs = Vec::with_capacity(fields.len());
+    for (key, value) in fields {
+        match value {
+            Value::Array(values) => {
+                for value in values {
+                    if let Some(value) = scalar(&key, value)? {
+                        pairs.push((key.clone(), value));
+                    }
+                }
+            }
+            value => {
+                if let Some(value) = scalar(&key, value)? {
+                    pairs.push((key, value));
+                }
+            }
+        }
+    }
+    Ok(pairs)
+}
+
+fn scalar(key: &str, value: Value) -> Result<Option<String>, JsonError> {
+    Ok(match value {
+        Value::Null => None,
+        Value::Bool(b) => Some(b.to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::String(s) => Some(s),
+        Value::Array(_) | Value::Object(_) => {
+            return Err(JsonError::custom(format!(
+                "field `{}` cannot be encoded in a form",
+                key
+            )))
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn pairs(v: &[(&str, &str)]) -> Vec<(String, String)> {
+        v.iter()
+            .map(|&(k, v)| (k.to_owned(), v.to_owned()))
+            .collect()
+    }
+
+    #[test]
+    fn encode_scalars() {
+        assert_eq!(
+            to_pairs(&json!({
+                "a": "x",
+                "b": 3,
+                "c": true,
+                "d": null,
+            }))
+            .unwrap(),
+            pairs(&[("a", "x"), ("b", "3"), ("c", "true")])
+        );
+    }
+
+    #[test]
+    fn encode_arrays() {
+        assert_eq!(
+            to_pairs(&json!({ "ids": ["1", "2"], "empty": [] })).unwrap(),
+            pairs(&[("ids", "1"), ("ids", "2")])
+        );
+    }
+
+    #[test]
+    fn reject_nested() {
+        to_pairs(&json!({ "a": { "b": 1 } })).unwrap_err();
+        to_pairs(&json!({ "a": [[1]] })).unwrap_err();
+        to_pairs(&json!([1])).unwrap_err();
+    }
+}
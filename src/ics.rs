@@ -0,0 +1,351 @@
+//! # iCalendar export
+//!
+//! Converts [`Course`]s (and optionally their side bar [`Event`]) to an
+//! iCalendar ([RFC 5545](https://datatracker.ietf.org/doc/html/rfc5545)) file.
+//!
+//! Celcat sends local times without any offset, which are always in the
+//! Europe/Paris time zone, so every timed event references the `VTIMEZONE`
+//! written at the top of the calendar.
+
+use std::fmt;
+
+use chrono::{NaiveDateTime, NaiveTime, Utc};
+
+use crate::{
+    entities::ResourceType,
+    fetchable::{
+        calendar::{description_lines, CalendarData, Course},
+        event::Event,
+    },
+};
+
+const PRODID: &str = concat!(
+    "-//cy-celcat//cy-celcat ",
+    env!("CARGO_PKG_VERSION"),
+    "//FR"
+);
+
+const TZID: &str = "Europe/Paris";
+
+const VTIMEZONE: &[&str] = &[
+    "BEGIN:VTIMEZONE",
+    "TZID:Europe/Paris",
+    "BEGIN:DAYLIGHT",
+    "TZOFFSETFROM:+0100",
+    "TZOFFSETTO:+0200",
+    "TZNAME:CEST",
+    "DTSTART:19700329T020000",
+    "RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=-1SU",
+    "END:DAYLIGHT",
+    "BEGIN:STANDARD",
+    "TZOFFSETFROM:+0200",
+    "TZOFFSETTO:+0100",
+    "TZNAME:CET",
+    "DTSTART:19701025T030000",
+    "RRULE:FREQ=YEARLY;BYMONTH=10;BYDAY=-1SU",
+    "END:STANDARD",
+    "END:VTIMEZONE",
+];
+
+/// An iCalendar `VCALENDAR`, rendered with its [`Display`](fmt::Display) implementation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VCalendar {
+    stamp: NaiveDateTime,
+    events: Vec<Vec<String>>,
+}
+
+impl VCalendar {
+    /// Creates an empty calendar, stamped with the current time.
+    pub fn new() -> Self {
+        Self::with_timestamp(Utc::now().naive_utc())
+    }
+
+    /// Creates an empty calendar, using `stamp` (in UTC) as the `DTSTAMP` of every event.
+    pub fn with_timestamp(stamp: NaiveDateTime) -> Self {
+        Self {
+            stamp,
+            events: Vec::new(),
+        }
+    }
+
+    /// Adds a `VEVENT` for `course`.
+    ///
+    /// The summary is the module, or the first line of the description without one.
+    /// The location and the organizer can only be known from the side bar `event`.
+    pub fn push(&mut self, course: &Course, event: Option<&Event>) {
+        let mut lines = vec![
+            "BEGIN:VEVENT".to_owned(),
+            format!("UID:{}@cy-celcat", escape(&course.id.0)),
+            format!("DTSTAMP:{}Z", self.stamp.format("%Y%m%dT%H%M%S")),
+        ];
+
+        if course.all_day {
+            let start = course.start.date();
+            let end = course
+                .end
+                .map(|e| {
+                    if e.time() == NaiveTime::from_hms(0, 0, 0) {
+                        e.date()
+                    } else {
+                        e.date().succ()
+                    }
+                })
+                .filter(|&e| e > start)
+                .unwrap_or_else(|| start.succ());
+            lines.push(format!("DTSTART;VALUE=DATE:{}", start.format("%Y%m%d")));
+            lines.push(format!("DTEND;VALUE=DATE:{}", end.format("%Y%m%d")));
+        } else {
+            lines.push(format!("DTSTART;TZID={}:{}", TZID, local(&course.start)));
+            if let Some(end) = &course.end {
+                lines.push(format!("DTEND;TZID={}:{}", TZID, local(end)));
+            }
+        }
+
+        let description: Vec<_> = description_lines(&course.description).collect();
+        let module = event
+            .and_then(Event::module)
+            .map(|(_, name)| name)
+            .or_else(|| {
+                let desc = course.parse_description();
+                desc.modules
+                    .into_iter()
+                    .next()
+                    .map(|(id, name)| format!("{} [{}]", name, id.0))
+            });
+        if let Some(summary) = module.as_ref().or_else(|| description.first()) {
+            lines.push(format!("SUMMARY:{}", escape(summary)));
+        }
+        if !description.is_empty() {
+            lines.push(format!("DESCRIPTION:{}", escape(&description.join("\n"))));
+        }
+        if let Some(category) = &course.event_category {
+            lines.push(format!("CATEGORIES:{}", escape(category)));
+        }
+
+        let rooms = event
+            .map(|e| {
+                e.rooms()
+                    .into_iter()
+                    .map(|(_, name)| name)
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        let location = if rooms.is_empty() {
+            course.sites.clone().unwrap_or_default()
+        } else {
+            rooms
+        };
+        if !location.is_empty() {
+            lines.push(format!("LOCATION:{}", escape(&location.join(", "))));
+        }
+
+        let teachers = event.map(Event::teachers).unwrap_or_default();
+        for (i, (id, name)) in teachers.iter().enumerate() {
+            let name = if name.is_empty() { &id.0 } else { name };
+            let property = if i == 0 {
+                "ORGANIZER"
+            } else {
+                "ATTENDEE;ROLE=CHAIR"
+            };
+            lines.push(format!(
+                "{};CN=\"{}\":urn:celcat:staff:{}",
+                property,
+                name.replace('"', "'"),
+                id.0
+            ));
+        }
+
+        lines.push("END:VEVENT".to_owned());
+        self.events.push(lines);
+    }
+}
+
+impl Default for VCalendar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> From<&CalendarData<T>> for VCalendar
+where
+    T: ResourceType,
+{
+    fn from(data: &CalendarData<T>) -> Self {
+        let mut cal = Self::new();
+        for course in &data.courses {
+            cal.push(course, None);
+        }
+        cal
+    }
+}
+
+impl fmt::Display for VCalendar {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let header = [
+            "BEGIN:VCALENDAR",
+            "VERSION:2.0",
+            &format!("PRODID:{}", PRODID),
+            "CALSCALE:GREGORIAN",
+        ];
+        let lines = header
+            .iter()
+            .copied()
+            .chain(VTIMEZONE.iter().copied())
+            .chain(self.events.iter().flatten().map(String::as_str))
+            .chain(std::iter::once("END:VCALENDAR"));
+        for line in lines {
+            write_folded(f, line)?;
+        }
+        Ok(())
+    }
+}
+
+fn local(dt: &NaiveDateTime) -> impl fmt::Display + '_ {
+    dt.format("%Y%m%dT%H%M%S")
+}
+
+/// Escapes a `TEXT` value.
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            ';' => out.push_str("\\;"),
+            ',' => out.push_str("\\,"),
+            '\n' => out.push_str("\\n"),
+            '\r' => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Writes a content line, folded so that no line is longer than 75 octets.
+fn write_folded(f: &mut fmt::Formatter, line: &str) -> fmt::Result {
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            f.write_str("\r\n ")?;
+            width = 1;
+        }
+        width += c.len_utf8();
+        write!(f, "{}", c)?;
+    }
+    f.write_str("\r\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fetchable::{calendar::tests::course_json, event::tests::element_json};
+    use chrono::NaiveDate;
+    use serde_json::{from_value, json};
+
+    fn course() -> Course {
+        let mut json = course_json("-1347128091:-662573064:1:42367:4");
+        json["end"] = json!("2021-09-22T17:45:00");
+        json["description"] =
+            json!("CM\r\n\r\n<br />DROIT CIVIL [1BAIJU1M]\r\n\r\n<br />CHENES; AMPHI, A");
+        json["department"] = json!("1 : UFR DROIT");
+        json["eventCategory"] = json!("CM");
+        json["sites"] = json!(["CHENES"]);
+        json["modules"] = json!(["1BAIJU1M"]);
+        json["registerStatus"] = json!(2);
+        from_value(json).unwrap()
+    }
+
+    fn render(course: &Course, event: Option<&Event>) -> String {
+        let mut cal = VCalendar::with_timestamp(NaiveDate::from_ymd(2021, 9, 1).and_hms(8, 0, 0));
+        cal.push(course, event);
+        cal.to_string()
+    }
+
+    #[test]
+    fn timed_course() {
+        let ics = render(&course(), None);
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+        assert!(ics.ends_with("END:VEVENT\r\nEND:VCALENDAR\r\n"));
+        assert!(ics.contains("\r\nTZID:Europe/Paris\r\n"));
+        assert!(ics.contains("\r\nUID:-1347128091:-662573064:1:42367:4@cy-celcat\r\n"));
+        assert!(ics.contains("\r\nDTSTAMP:20210901T080000Z\r\n"));
+        assert!(ics.contains("\r\nDTSTART;TZID=Europe/Paris:20210922T143000\r\n"));
+        assert!(ics.contains("\r\nDTEND;TZID=Europe/Paris:20210922T174500\r\n"));
+        assert!(ics.contains("\r\nSUMMARY:DROIT CIVIL [1BAIJU1M]\r\n"));
+        assert!(
+            ics.contains("\r\nDESCRIPTION:CM\\nDROIT CIVIL [1BAIJU1M]\\nCHENES\\; AMPHI\\, A\r\n")
+        );
+        assert!(ics.contains("\r\nLOCATION:CHENES\r\n"));
+    }
+
+    #[test]
+    fn course_without_module() {
+        let mut course = course();
+        course.description = "R&Eacute;UNION<br />CHENES; AMPHI, A".to_owned();
+        let ics = render(&course, None);
+        assert!(ics.contains("\r\nSUMMARY:RÉUNION\r\n"));
+        assert!(ics.contains("\r\nDESCRIPTION:RÉUNION\\nCHENES\\; AMPHI\\, A\r\n"));
+    }
+
+    #[test]
+    fn all_day_course() {
+        let mut course = course();
+        course.all_day = true;
+        course.end = None;
+        let ics = render(&course, None);
+        assert!(ics.contains("\r\nDTSTART;VALUE=DATE:20210922\r\n"));
+        assert!(ics.contains("\r\nDTEND;VALUE=DATE:20210923\r\n"));
+    }
+
+    #[test]
+    fn course_with_event() {
+        let elements = vec![
+            element_json(
+                Some("Matière"),
+                "Droit civil [1BAIJU1M]",
+                Some("1BAIJU1M"),
+                100,
+                Some("a-start-end"),
+            ),
+            element_json(
+                Some("Salles"),
+                "A ROOM",
+                Some("1172982"),
+                102,
+                Some("a-start"),
+            ),
+            element_json(
+                None,
+                "AN ANOTHER ROOM",
+                Some("1172981"),
+                102,
+                Some("a-end-0"),
+            ),
+            element_json(
+                Some("Enseignant"),
+                "SOME BODY",
+                Some("012345"),
+                101,
+                Some("a-start-end"),
+            ),
+        ];
+        let event: Event = from_value(json!({
+            "federationId": null,
+            "entityType": 0,
+            "elements": elements
+        }))
+        .unwrap();
+        let ics = render(&course(), Some(&event));
+        assert!(ics.contains("\r\nSUMMARY:Droit civil [1BAIJU1M]\r\n"));
+        assert!(ics.contains("\r\nLOCATION:A ROOM\\, AN ANOTHER ROOM\r\n"));
+        assert!(ics.contains("\r\nORGANIZER;CN=\"SOME BODY\":urn:celcat:staff:012345\r\n"));
+    }
+
+    #[test]
+    fn fold_long_lines() {
+        let mut course = course();
+        course.description = "é".repeat(100);
+        let ics = render(&course, None);
+        assert!(ics.split("\r\n").all(|l| l.len() <= 75));
+        assert!(ics.contains("\r\n é"));
+    }
+}
@@ -1,9 +1,16 @@
 #![doc = include_str!("../README.md")]
 
+#[cfg(feature = "blocking")]
+pub mod blocking;
 pub mod entities;
 #[cfg(feature = "fetch")]
 pub mod fetch;
 pub mod fetchable;
+#[cfg(feature = "fetch")]
+mod form;
+pub mod ics;
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
 
 pub use entities::*;
 #[cfg(feature = "fetch")]
@@ -0,0 +1,340 @@
+//! # Mock Celcat server
+//!
+//! A local HTTP server emulating the endpoints used by [`Celcat`](crate::Celcat),
+//! to test it without network.
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! use celcat::{mock::{Fixtures, MockServer}, Celcat};
+//!
+//! let server = MockServer::start(Fixtures::default()).await?;
+//! let mut celcat = Celcat::new(server.address()).await?;
+//! celcat.login("username", "password").await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    collections::{HashMap, HashSet},
+    convert::Infallible,
+    io,
+    net::{SocketAddr, TcpListener},
+    sync::{Arc, Mutex},
+};
+
+use hyper::{
+    header,
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use serde_json::{json, Value};
+use tokio::sync::oneshot;
+
+const SESSION_COOKIE: &str = "CelcatSession";
+const ANTIFORGERY_COOKIE: &str = "__RequestVerificationToken";
+
+/// The data served by a [`MockServer`].
+#[derive(Debug, Clone)]
+pub struct Fixtures {
+    pub username: String,
+    pub password: String,
+    /// The `__RequestVerificationToken` of the first login form.
+    ///
+    /// Each following form gets a new token, suffixed with `-2`, `-3`..., which is
+    /// only accepted along with the cookie set with it.
+    pub token: String,
+    /// The courses sent back by `GetCalendarData`, whatever the request.
+    pub calendar: Vec<Value>,
+    /// The events sent back by `GetSideBarEvent`, by course ID.
+    pub events: HashMap<String, Value>,
+    /// The resources sent back by `ReadResourceListItems`, whatever their type.
+    ///
+    /// They are filtered by `searchTerm` on their `text`, and paginated.
+    pub resources: Vec<Value>,
+}
+
+impl Default for Fixtures {
+    fn default() -> Self {
+        Self {
+            username: "username".to_owned(),
+            password: "password".to_owned(),
+            token: "mock-token".to_owned(),
+            calendar: Vec::new(),
+            events: HashMap::new(),
+            resources: Vec::new(),
+        }
+    }
+}
+
+/// A request received by a [`MockServer`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReceivedRequest {
+    pub method: Method,
+    pub path: String,
+    pub form: Vec<(String, String)>,
+}
+
+#[derive(Debug)]
+struct State {
+    fixtures: Fixtures,
+    sessions: HashSet<String>,
+    next_session: u64,
+    /// The token issued with each antiforgery cookie.
+    tokens: HashMap<String, String>,
+    statuses: HashMap<String, StatusCode>,
+    requests: Vec<ReceivedRequest>,
+}
+
+/// A running mock Celcat server, stopped when dropped.
+///
+/// It must be started from a Tokio runtime.
+#[derive(Debug)]
+pub struct MockServer {
+    address: String,
+    state: Arc<Mutex<State>>,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+impl MockServer {
+    /// Starts a server on a random local port.
+    pub async fn start(fixtures: Fixtures) -> io::Result<Self> {
+        let listener = TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0)))?;
+        listener.set_nonblocking(true)?;
+        let address = format!("http://{}", listener.local_addr()?);
+
+        let state = Arc::new(Mutex::new(State {
+            fixtures,
+            sessions: HashSet::new(),
+            next_session: 0,
+            tokens: HashMap::new(),
+            statuses: HashMap::new(),
+            requests: Vec::new(),
+        }));
+
+        let service_state = Arc::clone(&state);
+        let make_svc = make_service_fn(move |_| {
+            let state = Arc::clone(&service_state);
+            async move { Ok::<_, Infallible>(service_fn(move |req| handle(Arc::clone(&state), req))) }
+        });
+        let (shutdown, rx) = oneshot::channel();
+        let server = Server::from_tcp(listener)
+            .map_err(io::Error::other)?
+            .serve(make_svc)
+            .with_graceful_shutdown(async {
+                rx.await.ok();
+            });
+        tokio::spawn(server);
+
+        Ok(Self {
+            address,
+            state,
+            shutdown: Some(shutdown),
+        })
+    }
+
+    /// The address to give to [`Celcat::new`](crate::Celcat::new).
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// Invalidates every session, as if they had expired.
+    pub fn expire_sessions(&self) {
+        self.state.lock().unwrap().sessions.clear();
+    }
+
+    /// Makes every request to `/Home/{method}` fail with `status`,
+    /// or restores the normal behaviour with `None`.
+    pub fn set_status(&self, method: &str, status: Option<StatusCode>) {
+        let statuses = &mut self.state.lock().unwrap().statuses;
+        match status {
+            Some(status) => statuses.insert(method.to_owned(), status),
+            None => statuses.remove(method),
+        };
+    }
+
+    /// Every request received so far.
+    pub fn requests(&self) -> Vec<ReceivedRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            shutdown.send(()).ok();
+        }
+    }
+}
+
+async fn handle(
+    state: Arc<Mutex<State>>,
+    req: Request<Body>,
+) -> Result<Response<Body>, Infallible> {
+    let method = req.method().clone();
+    let path = req.uri().path().to_owned();
+    let cookie = |cookie: &str| {
+        req.headers()
+            .get_all(header::COOKIE)
+            .iter()
+            .filter_map(|h| h.to_str().ok())
+            .flat_map(|h| h.split(';'))
+            .filter_map(|c| c.trim().split_once('='))
+            .find(|&(name, _)| name == cookie)
+            .map(|(_, value)| value.to_owned())
+    };
+    let session = cookie(SESSION_COOKIE);
+    let antiforgery = cookie(ANTIFORGERY_COOKIE);
+    let body = hyper::body::to_bytes(req.into_body())
+        .await
+        .unwrap_or_default();
+    let form: Vec<(String, String)> = serde_urlencoded::from_bytes(&body).unwrap_or_default();
+
+    let mut state = state.lock().unwrap();
+    state.requests.push(ReceivedRequest {
+        method: method.clone(),
+        path: path.clone(),
+        form: form.clone(),
+    });
+    let field = |name: &str| {
+        form.iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+    };
+
+    let res = match (&method, path.as_str()) {
+        (&Method::GET, "/LdapLogin") => {
+            // Each visit issues a new token, only valid with its cookie
+            let n = state.tokens.len() + 1;
+            let token = match n {
+                1 => state.fixtures.token.clone(),
+                n => format!("{}-{}", state.fixtures.token, n),
+            };
+            state.tokens.insert(n.to_string(), token.clone());
+            let mut res = html(StatusCode::OK, &login_page(&token, None));
+            res.headers_mut().insert(
+                header::SET_COOKIE,
+                format!("{}={}; Path=/; HttpOnly", ANTIFORGERY_COOKIE, n)
+                    .parse()
+                    .unwrap(),
+            );
+            res
+        }
+        (&Method::POST, "/LdapLogin/Logon") => {
+            let token = antiforgery.and_then(|c| state.tokens.get(&c)).cloned();
+            if token.is_none() || field("__RequestVerificationToken") != token.as_deref() {
+                html(StatusCode::BAD_REQUEST, "Bad Request")
+            } else if field("Name") == Some(&state.fixtures.username)
+                && field("Password") == Some(&state.fixtures.password)
+            {
+                state.next_session += 1;
+                let session = format!("session-{}", state.next_session);
+                state.sessions.insert(session.clone());
+                Response::builder()
+                    .status(StatusCode::FOUND)
+                    .header(header::LOCATION, "/Home")
+                    .header(
+                        header::SET_COOKIE,
+                        format!("{}={}; Path=/; HttpOnly", SESSION_COOKIE, session),
+                    )
+                    .body(Body::empty())
+                    .unwrap()
+            } else {
+                let page = login_page(
+                    token.as_deref().unwrap_or_default(),
+                    Some("Identifiant ou mot de passe incorrect"),
+                );
+                html(StatusCode::OK, &page)
+            }
+        }
+        (&Method::GET, "/Home") => html(StatusCode::OK, "<html><body>Calendar</body></html>"),
+        (&Method::POST, p) if p.starts_with("/Home/") => {
+            let name = &p["/Home/".len()..];
+            if !session.is_some_and(|s| state.sessions.contains(&s)) {
+                Response::builder()
+                    .status(StatusCode::FOUND)
+                    .header(
+                        header::LOCATION,
+                        format!("/LdapLogin?ReturnUrl=%2FHome%2F{}", name),
+                    )
+                    .body(Body::empty())
+                    .unwrap()
+            } else if let Some(&status) = state.statuses.get(name) {
+                html(status, "<html><body>Error</body></html>")
+            } else {
+                match name {
+                    "GetCalendarData" => {
+                        json_response(&Value::from(state.fixtures.calendar.clone()))
+                    }
+                    "GetSideBarEvent" => {
+                        match field("eventId").and_then(|id| state.fixtures.events.get(id)) {
+                            Some(event) => json_response(event),
+                            None => html(
+                                StatusCode::INTERNAL_SERVER_ERROR,
+                                "<html><body>Error</body></html>",
+                            ),
+                        }
+                    }
+                    "ReadResourceListItems" => {
+                        let term = field("searchTerm").unwrap_or_default().to_lowercase();
+                        let size = field("pageSize").and_then(|s| s.parse().ok()).unwrap_or(50);
+                        let page = field("pageNumber")
+                            .and_then(|s| s.parse().ok())
+                            .unwrap_or(1);
+                        let matching: Vec<_> = state
+                            .fixtures
+                            .resources
+                            .iter()
+                            .filter(|r| {
+                                r["text"]
+                                    .as_str()
+                                    .is_some_and(|t| t.to_lowercase().contains(&term))
+                            })
+                            .collect();
+                        let results: Vec<_> = matching
+                            .iter()
+                            .skip(size * (page.max(1) - 1))
+                            .take(size)
+                            .collect();
+                        json_response(&json!({
+                            "total": matching.len(),
+                            "results": results,
+                        }))
+                    }
+                    _ => html(StatusCode::NOT_FOUND, "<html><body>Not Found</body></html>"),
+                }
+            }
+        }
+        _ => html(StatusCode::NOT_FOUND, "<html><body>Not Found</body></html>"),
+    };
+    Ok(res)
+}
+
+fn login_page(token: &str, error: Option<&str>) -> String {
+    let error = error
+        .map(|e| {
+            format!(
+                r#"<div class="validation-summary-errors"><ul><li>{}</li></ul></div>"#,
+                e
+            )
+        })
+        .unwrap_or_default();
+    format!(
+        r#"<html><body><form action="/LdapLogin/Logon" method="post">{}<input name="__RequestVerificationToken" type="hidden" value="{}" /><input name="Name" type="text" /><input name="Password" type="password" /></form></body></html>"#,
+        error, token
+    )
+}
+
+fn html(status: StatusCode, body: &str) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "text/html; charset=utf-8")
+        .body(Body::from(body.to_owned()))
+        .unwrap()
+}
+
+fn json_response(value: &Value) -> Response<Body> {
+    Response::builder()
+        .header(header::CONTENT_TYPE, "application/json; charset=utf-8")
+        .body(Body::from(value.to_string()))
+        .unwrap()
+}